@@ -14,6 +14,7 @@ use log::{info, trace};
 use crate::jni_wrap::{
     indices::{indices_create, indices_increment_by, indices_to_string},
     internal::InternalCrackData,
+    util::classpath_option,
 };
 
 pub(crate) fn tasks(
@@ -54,8 +55,7 @@ fn task(
         let jvm_args = InitArgsBuilder::new()
             .version(jni::JNIVersion::V8)
             .option("-Xcheck:jni")
-            .option("-Djava.class.path=/home/earth/rust/DN")
-            // .option("-Djava.class_pw.path=/home/earth/password")
+            .option(classpath_option())
             .build()
             .into_report()
             .unwrap();
@@ -26,6 +26,14 @@ pub fn jvm() -> &'static Arc<JavaVM> {
     unsafe { JVM.as_ref().unwrap() }
 }
 
+/// `-Djava.class.path` option built from the first command line argument,
+/// the working directory if there is none.
+#[allow(dead_code)]
+pub fn classpath_option() -> String {
+    let classpath = std::env::args().nth(1).unwrap_or_else(|| ".".into());
+    format!("-Djava.class.path={}", classpath)
+}
+
 #[allow(dead_code)]
 pub fn call_java_abs(env: &mut JNIEnv, value: i32) -> i32 {
     env.call_static_method(
@@ -9,14 +9,18 @@ use jni::{
 fn main() {
     let mut handles = vec![];
 
+    // classpath from the first command line argument, the working directory if there is none
+    let classpath = std::env::args().nth(1).unwrap_or_else(|| ".".into());
+
     for tid in 0..10 {
+        let classpath = classpath.clone();
         let builder = thread::Builder::new().name(tid.to_string());
         let handle = builder
             .spawn(move || {
                 let jvm_args = InitArgsBuilder::new()
                     .version(jni::JNIVersion::V8)
                     .option("-Xcheck:jni")
-                    .option("-Djava.class.path=/home/earth/rust/DN")
+                    .option(format!("-Djava.class.path={}", classpath))
                     .build()
                     .into_report()
                     .unwrap();
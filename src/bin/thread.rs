@@ -26,6 +26,7 @@ use crate::jni_wrap::{
     result::CrackResult
 };
 use crate::jni_wrap::symbols::Builder;
+use crate::jni_wrap::util::classpath_option;
 
 fn main() {
     env_logger::Builder::new()
@@ -205,7 +206,8 @@ fn task(
                     let res = indices_increment_by(
                         params.crack_param().charset(),
                         &mut indices,
-                        1,
+                        // every thread started at its own offset, so it skips the others' combinations
+                        params.thread_count(),
                     );
                     if res.is_err() {
                         info!(
@@ -344,8 +346,7 @@ fn jvm() -> &'static Arc<JavaVM> {
         let jvm_args = InitArgsBuilder::new()
             .version(jni::JNIVersion::V8)
             .option("-Xcheck:jni")
-            .option("-Djava.class.path=/home/earth/rust/DN")
-            // .option("-Djava.class_pw.path=/home/earth/password")
+            .option(classpath_option())
             .build()
             .into_report()
             .unwrap_or_else(|e| panic!("{:#?}", e));
@@ -0,0 +1,71 @@
+use crate::attack::{Candidates, Progress};
+use crate::jni::indices::{indices_at, indices_increment_by, indices_to_string};
+use crate::symbols::combination_count;
+
+/// Every `step`th combination of the charset, starting at the `offset`th.
+pub(crate) struct BruteForce {
+    charset: Box<[char]>,
+    // None once the share is done
+    indices: Option<Box<[isize]>>,
+    step: u128,
+    done: u128,
+    total: u128,
+}
+
+impl BruteForce {
+    pub fn new(charset: &[char], min_length: u8, max_length: u8, offset: u128, step: u128) -> Self {
+        // only the progress needs it, parts are made of attacks with a checked keyspace
+        let keyspace = combination_count(charset, min_length, max_length).unwrap_or(u128::MAX);
+        Self {
+            charset: Box::from(charset),
+            // indices object, that each thread gets as starting point
+            indices: indices_at(charset, max_length, min_length, offset),
+            step,
+            done: 0,
+            total: keyspace.saturating_sub(offset).div_ceil(step),
+        }
+    }
+}
+
+impl Candidates for BruteForce {
+    fn next(&mut self, buf: &mut String) -> bool {
+        let Some(indices) = &mut self.indices else {
+            return false;
+        };
+        indices_to_string(buf, &self.charset, indices);
+        if indices_increment_by(&self.charset, indices, self.step).is_err() {
+            self.indices = None;
+        }
+        self.done += 1;
+        true
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.done,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_brute_force {
+    use super::*;
+    use crate::attack::tests_attack::assert_parts_cover_every_candidate_once;
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let charset = ['a', 'b', 'c'];
+        let expected = (0..1 + 3 + 9)
+            .map(|i| {
+                let mut buf = String::new();
+                indices_to_string(&mut buf, &charset, &indices_at(&charset, 2, 0, i).unwrap());
+                buf
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(expected[..3], ["", "a", "b"]);
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| {
+            BruteForce::new(&charset, 0, 2, offset, step)
+        });
+    }
+}
@@ -0,0 +1,255 @@
+use std::{io, sync::Arc};
+
+use crate::attack::indexed::{
+    digits_of, index_of_digits, radices_keyspace, Indexable, IndexedPart,
+};
+use crate::attack::Wordlist;
+use crate::symbols::KeyspaceOverflow;
+
+/// How the words of a combinator part are cased.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaseStyle {
+    /// The word as it is in the wordlist.
+    #[default]
+    AsIs,
+    /// `password`
+    Lower,
+    /// `Password`
+    Capitalized,
+    /// `PASSWORD`
+    Upper,
+}
+
+impl CaseStyle {
+    /// Appends `word` in this style to `buf`.
+    pub(crate) fn write(self, word: &str, buf: &mut String) {
+        match self {
+            CaseStyle::AsIs => buf.push_str(word),
+            CaseStyle::Lower => buf.extend(word.chars().flat_map(char::to_lowercase)),
+            CaseStyle::Upper => buf.extend(word.chars().flat_map(char::to_uppercase)),
+            CaseStyle::Capitalized => {
+                let mut chars = word.chars();
+                if let Some(first) = chars.next() {
+                    buf.extend(first.to_uppercase());
+                    buf.extend(chars.flat_map(char::to_lowercase));
+                }
+            }
+        }
+    }
+}
+
+/// One wordlist of a combinator & the case styles its words are tried in.
+#[derive(Debug, Clone)]
+struct Part {
+    // shared by the parts of all workers
+    words: Arc<[String]>,
+    styles: Vec<CaseStyle>,
+}
+
+/// Every combination of one word from each of several wordlists, e.g. `correct`, `horse`
+/// & `-` for `correct-horse`.
+///
+/// The words are read into memory upfront, so that the candidates can be indexed like the
+/// brute force indices: one digit for the word & one for the case style of every part,
+/// the last part changes fastest. Styles that give the same word, e.g. `Lower` for a word
+/// that's lowercase already, give the same candidate twice.
+#[derive(Debug, Clone)]
+pub struct Combinator {
+    parts: Vec<Part>,
+    separator: String,
+}
+
+impl Combinator {
+    /// One part per wordlist, in order, with the words as they are.
+    pub fn new(wordlists: &[Wordlist]) -> io::Result<Self> {
+        let parts = wordlists
+            .iter()
+            .map(|wordlist| {
+                Ok(Part {
+                    words: wordlist.words()?.into(),
+                    styles: vec![CaseStyle::AsIs],
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            parts,
+            separator: String::new(),
+        })
+    }
+
+    /// Put between the words of a candidate, nothing by default.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Tries every word of the `part`th wordlist in each of the styles.
+    /// Panics if there's no such part or no style.
+    pub fn with_case_styles(mut self, part: usize, styles: &[CaseStyle]) -> Self {
+        assert!(!styles.is_empty(), "A part needs at least one case style");
+        self.parts[part].styles = styles.to_vec();
+        self
+    }
+
+    /// Number of wordlists.
+    pub fn len(&self) -> usize {
+        self.parts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn words(&self, part: usize) -> &[String] {
+        &self.parts[part].words
+    }
+
+    pub fn case_styles(&self, part: usize) -> &[CaseStyle] {
+        &self.parts[part].styles
+    }
+
+    /// Exact number of candidates, the product of words times styles of all parts.
+    pub fn keyspace(&self) -> Result<u128, KeyspaceOverflow> {
+        radices_keyspace(&self.radices())
+    }
+
+    /// Writes the candidate at `index` into `buf`. `index` must be below the keyspace.
+    pub fn nth(&self, index: u128, buf: &mut String) {
+        buf.clear();
+        self.write(&digits_of(&self.radices(), index), buf);
+    }
+
+    /// The index of `candidate`, None if the combinator doesn't produce it.
+    /// If it does more than once, e.g. with styles that give the same word, the first one.
+    pub fn index_of(&self, candidate: &str) -> Option<u128> {
+        let mut digits = vec![];
+        if !self.find(candidate, &mut digits) {
+            return None;
+        }
+        index_of_digits(&self.radices(), &digits)
+    }
+
+    /// Depth first search for the digits of the parts from `digits.len() / 2` on that make up
+    /// `rest`, the smallest digits first.
+    fn find(&self, rest: &str, digits: &mut Vec<usize>) -> bool {
+        let part = digits.len() / 2;
+        let Some(Part { words, styles }) = self.parts.get(part) else {
+            return rest.is_empty();
+        };
+        let rest = if part > 0 {
+            match rest.strip_prefix(self.separator.as_str()) {
+                Some(rest) => rest,
+                None => return false,
+            }
+        } else {
+            rest
+        };
+
+        let mut styled = String::new();
+        for (w, word) in words.iter().enumerate() {
+            for (s, style) in styles.iter().enumerate() {
+                styled.clear();
+                style.write(word, &mut styled);
+                if let Some(rest) = rest.strip_prefix(styled.as_str()) {
+                    digits.extend([w, s]);
+                    if self.find(rest, digits) {
+                        return true;
+                    }
+                    digits.truncate(digits.len() - 2);
+                }
+            }
+        }
+        false
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(&self, offset: u128, step: u128) -> IndexedPart<Combinator> {
+        IndexedPart::new(self.clone(), offset, step)
+    }
+}
+
+impl Indexable for Combinator {
+    fn radices(&self) -> Vec<usize> {
+        self.parts
+            .iter()
+            .flat_map(|part| [part.words.len(), part.styles.len()])
+            .collect()
+    }
+
+    fn write(&self, digits: &[usize], buf: &mut String) {
+        for (i, (part, digits)) in self.parts.iter().zip(digits.chunks(2)).enumerate() {
+            if i > 0 {
+                buf.push_str(&self.separator);
+            }
+            part.styles[digits[1]].write(&part.words[digits[0]], buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_combinator {
+    use super::*;
+    use crate::attack::tests_attack::{assert_parts_cover_every_candidate_once, TempFile};
+
+    fn all(combinator: &Combinator) -> Vec<String> {
+        let mut buf = String::new();
+        (0..combinator.keyspace().unwrap())
+            .map(|i| {
+                combinator.nth(i, &mut buf);
+                buf.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_combinations() {
+        let files = [
+            TempFile::new("combinator_first", "correct\nhOrse\n"),
+            TempFile::new("combinator_second", "battery\nstaple\nx\n"),
+        ];
+        let wordlists = files.iter().map(TempFile::wordlist).collect::<Vec<_>>();
+
+        let combinator = Combinator::new(&wordlists).unwrap().with_separator("-");
+        assert_eq!(combinator.keyspace(), Ok(6));
+        assert_eq!(all(&combinator)[..2], ["correct-battery", "correct-staple"]);
+        assert_eq!(all(&combinator)[5], "hOrse-x");
+
+        let combinator = Combinator::new(&wordlists).unwrap().with_separator(" + ");
+        assert_eq!(all(&combinator)[1], "correct + staple");
+        assert_eq!(combinator.index_of("correct + staple"), Some(1));
+        assert_eq!(combinator.index_of("correct+staple"), None);
+
+        let styles = [CaseStyle::Lower, CaseStyle::Capitalized, CaseStyle::Upper];
+        let combinator = Combinator::new(&wordlists)
+            .unwrap()
+            .with_case_styles(0, &styles);
+        assert_eq!(combinator.keyspace(), Ok(18));
+        let candidates = all(&combinator);
+        assert_eq!(candidates[9..12], ["horsebattery", "horsestaple", "horsex"]);
+        assert_eq!(candidates[12], "Horsebattery");
+        assert_eq!(candidates[15], "HORSEbattery");
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let files = [
+            TempFile::new("combinator_a", "a\nbb\n"),
+            TempFile::new("combinator_b", "c\nd\ne\n"),
+            TempFile::new("combinator_c", "f\n"),
+        ];
+        let wordlists = files.iter().map(TempFile::wordlist).collect::<Vec<_>>();
+        let combinator = Combinator::new(&wordlists)
+            .unwrap()
+            .with_separator(" ")
+            .with_case_styles(1, &[CaseStyle::AsIs, CaseStyle::Upper]);
+        let expected = all(&combinator);
+        assert_eq!(expected.len(), 12);
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| {
+            combinator.part(offset, step)
+        });
+    }
+}
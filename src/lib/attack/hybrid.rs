@@ -0,0 +1,160 @@
+use std::{io, sync::Arc};
+
+use crate::attack::indexed::{
+    digits_of, index_of_digits, radices_keyspace, Indexable, IndexedPart,
+};
+use crate::attack::{Mask, Wordlist};
+use crate::symbols::KeyspaceOverflow;
+
+/// Every word of a wordlist combined with every candidate of a mask,
+/// e.g. `password` & `?d?d` for `password00` to `password99`.
+///
+/// The words are read into memory upfront, so that the candidates can be indexed:
+/// candidate `i` is word `i / mask.keyspace()` with mask candidate `i % mask.keyspace()`
+/// (or the other way round, if the mask comes first).
+#[derive(Debug, Clone)]
+pub struct Hybrid {
+    // shared by the parts of all workers
+    words: Arc<[String]>,
+    mask: Mask,
+    mask_first: bool,
+}
+
+impl Hybrid {
+    /// The words followed by the mask.
+    pub fn wordlist_mask(wordlist: &Wordlist, mask: Mask) -> io::Result<Self> {
+        Ok(Self {
+            words: wordlist.words()?.into(),
+            mask,
+            mask_first: false,
+        })
+    }
+
+    /// The mask followed by the words.
+    pub fn mask_wordlist(mask: Mask, wordlist: &Wordlist) -> io::Result<Self> {
+        Ok(Self {
+            mask_first: true,
+            ..Self::wordlist_mask(wordlist, mask)?
+        })
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    pub fn mask(&self) -> &Mask {
+        &self.mask
+    }
+
+    pub fn mask_first(&self) -> bool {
+        self.mask_first
+    }
+
+    /// Exact number of candidates, the product of the words & the mask's keyspace.
+    pub fn keyspace(&self) -> Result<u128, KeyspaceOverflow> {
+        radices_keyspace(&self.radices())
+    }
+
+    /// Writes the candidate at `index` into `buf`. `index` must be below the keyspace.
+    pub fn nth(&self, index: u128, buf: &mut String) {
+        buf.clear();
+        self.write(&digits_of(&self.radices(), index), buf);
+    }
+
+    /// The index of `candidate`, None if it isn't a word with a candidate of the mask.
+    pub fn index_of(&self, candidate: &str) -> Option<u128> {
+        let chars = candidate.chars().collect::<Vec<_>>();
+        let word_len = chars.len().checked_sub(self.mask.len())?;
+        let (mask, word) = if self.mask_first {
+            chars.split_at(self.mask.len())
+        } else {
+            let (word, mask) = chars.split_at(word_len);
+            (mask, word)
+        };
+        let word = word.iter().collect::<String>();
+        let word = self.words.iter().position(|w| *w == word)?;
+        let mut digits = self.mask.parse(&mask.iter().collect::<String>())?;
+        if self.mask_first {
+            digits.push(word);
+        } else {
+            digits.insert(0, word);
+        }
+        index_of_digits(&self.radices(), &digits)
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(&self, offset: u128, step: u128) -> IndexedPart<Hybrid> {
+        IndexedPart::new(self.clone(), offset, step)
+    }
+}
+
+impl Indexable for Hybrid {
+    fn radices(&self) -> Vec<usize> {
+        let mut radices = self.mask.radices();
+        if self.mask_first {
+            radices.push(self.words.len());
+        } else {
+            radices.insert(0, self.words.len());
+        }
+        radices
+    }
+
+    fn write(&self, digits: &[usize], buf: &mut String) {
+        if self.mask_first {
+            let (mask, word) = digits.split_at(digits.len() - 1);
+            self.mask.write(mask, buf);
+            buf.push_str(&self.words[word[0]]);
+        } else {
+            buf.push_str(&self.words[digits[0]]);
+            self.mask.write(&digits[1..], buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_hybrid {
+    use super::*;
+    use crate::attack::tests_attack::{assert_parts_cover_every_candidate_once, TempFile};
+
+    fn all(hybrid: &Hybrid) -> Vec<String> {
+        let mut buf = String::new();
+        (0..hybrid.keyspace().unwrap())
+            .map(|i| {
+                hybrid.nth(i, &mut buf);
+                buf.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_order() {
+        let file = TempFile::new("hybrid_order", "foo\n# comment\nbar\n");
+        let wordlist = file.wordlist();
+        let mask = Mask::new("?d").unwrap();
+
+        let hybrid = Hybrid::wordlist_mask(&wordlist, mask.clone()).unwrap();
+        assert_eq!(hybrid.keyspace(), Ok(20));
+        let candidates = all(&hybrid);
+        assert_eq!(candidates[..2], ["foo0", "foo1"]);
+        assert_eq!(candidates[10..12], ["bar0", "bar1"]);
+
+        let hybrid = Hybrid::mask_wordlist(mask, &wordlist).unwrap();
+        assert_eq!(all(&hybrid)[..3], ["0foo", "0bar", "1foo"]);
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let file = TempFile::new("hybrid_parts", "a\nbb\nccc\n");
+        let wordlist = file.wordlist();
+        let mask = Mask::with_classes("?1?d", &[&['x', 'y']]).unwrap();
+        for hybrid in [
+            Hybrid::wordlist_mask(&wordlist, mask.clone()).unwrap(),
+            Hybrid::mask_wordlist(mask.clone(), &wordlist).unwrap(),
+        ] {
+            let expected = all(&hybrid);
+            assert_parts_cover_every_candidate_once(&expected, |offset, step| {
+                hybrid.part(offset, step)
+            });
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use crate::attack::{Candidates, Progress};
+use crate::jni::indices::digits_increment_by;
+use crate::symbols::KeyspaceOverflow;
+
+/// A source whose candidates are numbered like a number with one digit per part,
+/// the last digit changes fastest. E.g. one digit per mask position,
+/// or the word & the mask positions of a hybrid attack.
+pub(crate) trait Indexable: Clone + Send {
+    /// The number of values of each digit.
+    fn radices(&self) -> Vec<usize>;
+
+    /// Writes the candidate of the digits into `buf`.
+    fn write(&self, digits: &[usize], buf: &mut String);
+}
+
+/// Every `step`th candidate of an indexable source, starting at `offset`.
+/// Steps through the digits with the indices odometer, instead of dividing for every candidate.
+pub(crate) struct IndexedPart<S> {
+    source: S,
+    radices: Vec<usize>,
+    digits: Vec<usize>,
+    step: u128,
+    // false once the odometer overflowed
+    more: bool,
+    done: u128,
+    total: u128,
+}
+
+impl<S: Indexable> IndexedPart<S> {
+    pub fn new(source: S, offset: u128, step: u128) -> Self {
+        let radices = source.radices();
+        // only the progress needs it, parts are made of attacks with a checked keyspace
+        let keyspace = radices_keyspace(&radices).unwrap_or(u128::MAX);
+        let mut digits = vec![0; radices.len()];
+        let more = keyspace > 0 && digits_increment_by(&radices, &mut digits, offset).is_ok();
+        Self {
+            source,
+            radices,
+            digits,
+            step,
+            more,
+            done: 0,
+            total: keyspace.saturating_sub(offset).div_ceil(step),
+        }
+    }
+}
+
+impl<S: Indexable> Candidates for IndexedPart<S> {
+    fn next(&mut self, buf: &mut String) -> bool {
+        if !self.more {
+            return false;
+        }
+        buf.clear();
+        self.source.write(&self.digits, buf);
+        self.more = digits_increment_by(&self.radices, &mut self.digits, self.step).is_ok();
+        self.done += 1;
+        true
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.done,
+            total: self.total,
+        }
+    }
+}
+
+/// The product of the radices, i.e. the number of candidates.
+pub(crate) fn radices_keyspace(radices: &[usize]) -> Result<u128, KeyspaceOverflow> {
+    if radices.contains(&0) {
+        return Ok(0);
+    }
+    radices.iter().try_fold(1u128, |keyspace, radix| {
+        keyspace.checked_mul(*radix as u128).ok_or(KeyspaceOverflow)
+    })
+}
+
+/// The digits of the candidate at `index`, which must be below the product of the radices.
+pub(crate) fn digits_of(radices: &[usize], mut index: u128) -> Vec<usize> {
+    let mut digits = vec![0; radices.len()];
+    for (digit, radix) in digits.iter_mut().zip(radices).rev() {
+        let radix = *radix as u128;
+        *digit = (index % radix) as usize;
+        index /= radix;
+    }
+    digits
+}
+
+/// The inverse of [`digits_of`]. None if the index doesn't fit into a `u128`.
+pub(crate) fn index_of_digits(radices: &[usize], digits: &[usize]) -> Option<u128> {
+    radices
+        .iter()
+        .zip(digits)
+        .try_fold(0u128, |index, (radix, digit)| {
+            index
+                .checked_mul(*radix as u128)?
+                .checked_add(*digit as u128)
+        })
+}
+
+/// Exact number of candidates of [`Lengths`].
+pub(crate) fn lengths_keyspace(
+    width: usize,
+    suffix: &[usize],
+    min_length: usize,
+    max_length: usize,
+) -> Result<u128, KeyspaceOverflow> {
+    let mut sum: u128 = 0;
+    for length in min_length..=max_length {
+        let mut radices = vec![width; length];
+        radices.extend(suffix);
+        sum = sum
+            .checked_add(radices_keyspace(&radices)?)
+            .ok_or(KeyspaceOverflow)?;
+    }
+    Ok(sum)
+}
+
+/// Every `step`th candidate of every length between a min & a max length, shortest first,
+/// starting at `offset`. A candidate of length `n` has `n` digits with `width` values each,
+/// followed by the digits of the suffix radices.
+pub(crate) struct Lengths {
+    width: usize,
+    suffix: Vec<usize>,
+    max_length: usize,
+    length: usize,
+    // the indices of the first candidate of the current length & the first one after it
+    length_start: u128,
+    length_end: u128,
+    radices: Vec<usize>,
+    digits: Vec<usize>,
+    index: u128,
+    step: u128,
+    more: bool,
+}
+
+impl Lengths {
+    pub fn new(
+        width: usize,
+        suffix: Vec<usize>,
+        min_length: usize,
+        max_length: usize,
+        offset: u128,
+        step: u128,
+    ) -> Self {
+        let mut lengths = Self {
+            length_end: Self::size(width, &suffix, min_length),
+            width,
+            suffix,
+            max_length,
+            length: min_length,
+            length_start: 0,
+            radices: vec![],
+            digits: vec![],
+            index: offset,
+            step,
+            more: true,
+        };
+        lengths.seek();
+        lengths
+    }
+
+    /// The digits of the current candidate, None once the share is done.
+    pub fn digits(&self) -> Option<&[usize]> {
+        self.more.then_some(&self.digits[..])
+    }
+
+    pub fn advance(&mut self) {
+        self.index = self.index.saturating_add(self.step);
+        if digits_increment_by(&self.radices, &mut self.digits, self.step).is_err() {
+            // the odometer overflowed into the next length
+            self.seek();
+        }
+    }
+
+    /// The number of candidates of one length, saturated, as the keyspace was checked before.
+    fn size(width: usize, suffix: &[usize], length: usize) -> u128 {
+        lengths_keyspace(width, suffix, length, length).unwrap_or(u128::MAX)
+    }
+
+    /// Moves on to the length of `index` & sets the digits from it.
+    fn seek(&mut self) {
+        while self.index >= self.length_end {
+            if self.length >= self.max_length {
+                self.more = false;
+                return;
+            }
+            self.length += 1;
+            self.length_start = self.length_end;
+            self.length_end =
+                self.length_end
+                    .saturating_add(Self::size(self.width, &self.suffix, self.length));
+        }
+        self.radices = vec![self.width; self.length];
+        self.radices.extend(&self.suffix);
+        self.digits = digits_of(&self.radices, self.index - self.length_start);
+    }
+}
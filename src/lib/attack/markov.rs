@@ -0,0 +1,516 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fmt::Write as _,
+    fs, io,
+    path::Path,
+    sync::Arc,
+};
+
+use thiserror::Error;
+
+use crate::attack::indexed::lengths_keyspace;
+use crate::attack::{Candidates, Progress, Wordlist};
+use crate::symbols::KeyspaceOverflow;
+
+/// Written at the top of every stats file.
+const HEADER: &str = "# tokio_pw markov stats: previous next count, \
+                      chars as code points, ^ is the start of a word";
+
+/// Errors while loading a stats file.
+#[derive(Debug, Error)]
+pub enum MarkovError {
+    #[error("line {0} isn't `previous next count`")]
+    InvalidLine(usize),
+    #[error("reading the stats file failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// How often each character follows each other character, or starts a word,
+/// counted over a corpus of real passwords.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkovModel {
+    // None is the start of a word
+    counts: BTreeMap<(Option<char>, char), u64>,
+}
+
+impl MarkovModel {
+    /// Counts every word of the corpus, comments & rules work like in a wordlist attack.
+    pub fn train(corpus: &Wordlist) -> io::Result<Self> {
+        let mut part = corpus.open_part(0, 1)?;
+        let mut counts = BTreeMap::new();
+        let mut word = String::new();
+        while part.next(&mut word) {
+            let mut previous = None;
+            for char in word.chars() {
+                *counts.entry((previous, char)).or_insert(0) += 1;
+                previous = Some(char);
+            }
+        }
+        part.take_error()?;
+        Ok(Self { counts })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MarkovError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a stats file. Empty lines & lines starting with `#` are skipped.
+    pub fn parse(stats: &str) -> Result<Self, MarkovError> {
+        let mut counts = BTreeMap::new();
+        for (i, line) in stats.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || MarkovError::InvalidLine(i + 1);
+            let fields = line.split(' ').collect::<Vec<_>>();
+            let [previous, next, count] = fields[..] else {
+                return Err(invalid());
+            };
+            let previous = match previous {
+                "^" => None,
+                code => Some(parse_char(code).ok_or_else(invalid)?),
+            };
+            let next = parse_char(next).ok_or_else(invalid)?;
+            let count = count.parse().map_err(|_| invalid())?;
+            counts.insert((previous, next), count);
+        }
+        Ok(Self { counts })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// How often `next` followed `previous`, or started a word if `previous` is None.
+    pub fn count(&self, previous: Option<char>, next: char) -> u64 {
+        self.counts.get(&(previous, next)).copied().unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for MarkovModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for ((previous, next), count) in &self.counts {
+            match previous {
+                Some(previous) => write!(f, "{}", *previous as u32)?,
+                None => f.write_char('^')?,
+            }
+            writeln!(f, " {} {}", *next as u32, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_char(code: &str) -> Option<char> {
+    char::from_u32(code.parse().ok()?)
+}
+
+/// The successors of the start of a word & of each char of a charset, with the probability
+/// of each, most likely first.
+#[derive(Debug)]
+struct Chain {
+    // the successors of the start of a word, followed by those of each char of the charset
+    successors: Vec<Box<[(char, f64)]>>,
+    // the successors index of each char
+    contexts: HashMap<char, usize>,
+}
+
+impl Chain {
+    /// Every count is one higher than in the model, so that chars that never followed
+    /// each other are unlikely, but not impossible. Ties keep the order of the charset.
+    fn new(model: &MarkovModel, charset: &[char]) -> Self {
+        let start = std::iter::once(None).chain(charset.iter().copied().map(Some));
+        let successors = start
+            .map(|previous| {
+                let counts = charset.iter().map(|next| model.count(previous, *next) + 1);
+                let total = counts.sum::<u64>() as f64;
+                let mut successors = charset
+                    .iter()
+                    .map(|next| (*next, (model.count(previous, *next) + 1) as f64 / total))
+                    .collect::<Vec<_>>();
+                successors.sort_by(|a, b| b.1.total_cmp(&a.1));
+                successors.into()
+            })
+            .collect();
+        Self {
+            successors,
+            contexts: charset
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (*c, i + 1))
+                .collect(),
+        }
+    }
+
+    /// The successors of the last char of `prefix`, those of the start of a word if it's empty.
+    fn successors(&self, prefix: &str) -> &[(char, f64)] {
+        let context = prefix.chars().last().map_or(0, |char| self.contexts[&char]);
+        &self.successors[context]
+    }
+
+    /// The probability of a whole candidate, None if it has a char outside the charset.
+    fn probability(&self, candidate: &str) -> Option<f64> {
+        let mut probability = 1.0;
+        for (i, char) in candidate.char_indices() {
+            let successors = self.successors(&candidate[..i]);
+            probability *= successors.iter().find(|(c, _)| *c == char)?.1;
+        }
+        Some(probability)
+    }
+
+    /// Number of candidates between the lengths at least as likely as `threshold`
+    /// that start with `prefix`, itself included.
+    fn count(
+        &self,
+        prefix: &mut String,
+        probability: f64,
+        threshold: f64,
+        lengths: (usize, usize),
+    ) -> Result<u128, KeyspaceOverflow> {
+        let length = prefix.chars().count();
+        let mut count = u128::from(length >= lengths.0);
+        if length == lengths.1 {
+            return Ok(count);
+        }
+        for (char, p) in self.successors(prefix).to_vec() {
+            if probability * p < threshold {
+                // the successors are sorted, the rest are even less likely
+                break;
+            }
+            prefix.push(char);
+            let longer = self.count(prefix, probability * p, threshold, lengths);
+            prefix.pop();
+            count = count.checked_add(longer?).ok_or(KeyspaceOverflow)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Brute force over the param's charset & lengths in the order of a Markov model:
+/// the most likely candidates as a whole first, e.g. "monkey" long before "mqxzjv".
+/// The probability of a candidate is the one of its first char starting a word, times
+/// those of each following char following the one before it.
+///
+/// The order only exists through a priority queue, so a crack runs it on one thread
+/// that feeds all workers. It's deterministic, so the candidates can still be split by index.
+#[derive(Debug, Clone)]
+pub struct Markov {
+    model: Arc<MarkovModel>,
+    threshold: f64,
+}
+
+impl Markov {
+    pub fn new(model: MarkovModel) -> Self {
+        Self {
+            model: Arc::new(model),
+            threshold: 0.0,
+        }
+    }
+
+    /// Only tries the candidates with a probability of at least `threshold`, between 0 & 1.
+    /// As the less likely candidates come last, they're simply never reached.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn model(&self) -> &MarkovModel {
+        &self.model
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Exact number of candidates over the charset & lengths.
+    /// With a threshold they're counted one by one, which takes about as long
+    /// as generating them.
+    pub fn keyspace(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+    ) -> Result<u128, KeyspaceOverflow> {
+        if self.threshold <= 0.0 {
+            return lengths_keyspace(charset.len(), &[], min_length.into(), max_length.into());
+        }
+        if self.threshold > 1.0 {
+            return Ok(0);
+        }
+        let lengths = (min_length.into(), max_length.into());
+        Chain::new(&self.model, charset).count(&mut String::new(), 1.0, self.threshold, lengths)
+    }
+
+    /// The candidate at `index`, None past the last one.
+    /// Walks the queue from the start, as the order only exists through it.
+    pub fn nth(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+        index: u128,
+    ) -> Option<String> {
+        let mut buf = String::new();
+        let mut part = self.part(charset, min_length, max_length, index, 1);
+        part.next(&mut buf).then_some(buf)
+    }
+
+    /// The index of `candidate`, None if it has a char outside the charset or is less likely
+    /// than the threshold. Walks the queue from the start, as the order only exists through it.
+    pub fn index_of(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+        candidate: &str,
+    ) -> Option<u128> {
+        let length = candidate.chars().count();
+        if length < min_length.into() || length > max_length.into() {
+            return None;
+        }
+        let probability = Chain::new(&self.model, charset).probability(candidate)?;
+        if probability < self.threshold {
+            return None;
+        }
+        let mut part = self.part(charset, min_length, max_length, 0, 1);
+        let mut buf = String::new();
+        let mut index = 0;
+        while part.next(&mut buf) {
+            if buf == candidate {
+                return Some(index);
+            }
+            index += 1;
+        }
+        None
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+        offset: u128,
+        step: u128,
+    ) -> MarkovPart {
+        let mut queue = BinaryHeap::new();
+        if self.threshold <= 1.0 {
+            queue.push(Node {
+                probability: 1.0,
+                parent: 1.0,
+                candidate: String::new(),
+                ranks: vec![],
+            });
+        }
+        MarkovPart {
+            chain: Chain::new(&self.model, charset),
+            queue,
+            threshold: self.threshold,
+            min_length: min_length.into(),
+            max_length: max_length.into(),
+            index: 0,
+            offset,
+            step,
+            done: 0,
+            total: self
+                .keyspace(charset, min_length, max_length)
+                .unwrap_or(u128::MAX)
+                .saturating_sub(offset)
+                .div_ceil(step),
+        }
+    }
+}
+
+/// A prefix in the queue & the rank of each of its chars among the successors of the one before.
+#[derive(Debug)]
+struct Node {
+    probability: f64,
+    // the probability of the prefix without its last char
+    parent: f64,
+    candidate: String,
+    ranks: Vec<usize>,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the most likely first, ties in the order of the ranks
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.ranks.cmp(&self.ranks))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+/// The share of a Markov brute force of one worker.
+pub(crate) struct MarkovPart {
+    chain: Chain,
+    queue: BinaryHeap<Node>,
+    threshold: f64,
+    min_length: usize,
+    max_length: usize,
+    // the index of the next candidate of the queue
+    index: u128,
+    offset: u128,
+    step: u128,
+    done: u128,
+    total: u128,
+}
+
+impl MarkovPart {
+    /// Takes the most likely prefix & queues its successors: the prefix with the last char
+    /// replaced by the next less likely one & the prefix followed by its most likely successor.
+    /// Neither is more likely than the prefix, so every prefix comes out in order & once.
+    fn pop(&mut self) -> Option<Node> {
+        let node = self.queue.pop()?;
+        if let Some(last) = node.ranks.last() {
+            let mut candidate = node.candidate.clone();
+            candidate.pop();
+            if let Some((char, p)) = self.chain.successors(&candidate).get(last + 1) {
+                candidate.push(*char);
+                let mut ranks = node.ranks.clone();
+                *ranks.last_mut().unwrap() += 1;
+                self.push(node.parent * p, node.parent, candidate, ranks);
+            }
+        }
+        if node.ranks.len() < self.max_length {
+            if let Some((char, p)) = self.chain.successors(&node.candidate).first() {
+                let mut candidate = node.candidate.clone();
+                candidate.push(*char);
+                let mut ranks = node.ranks.clone();
+                ranks.push(0);
+                self.push(node.probability * p, node.probability, candidate, ranks);
+            }
+        }
+        Some(node)
+    }
+
+    fn push(&mut self, probability: f64, parent: f64, candidate: String, ranks: Vec<usize>) {
+        if probability >= self.threshold {
+            self.queue.push(Node {
+                probability,
+                parent,
+                candidate,
+                ranks,
+            });
+        }
+    }
+}
+
+impl Candidates for MarkovPart {
+    fn next(&mut self, buf: &mut String) -> bool {
+        loop {
+            let Some(node) = self.pop() else {
+                return false;
+            };
+            // shorter prefixes are only queued for their successors
+            if node.ranks.len() < self.min_length {
+                continue;
+            }
+            let index = self.index;
+            self.index += 1;
+            if index < self.offset || !(index - self.offset).is_multiple_of(self.step) {
+                continue;
+            }
+
+            buf.clear();
+            buf.push_str(&node.candidate);
+            self.done += 1;
+            return true;
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.done,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_markov {
+    use super::*;
+    use crate::attack::tests_attack::{assert_parts_cover_every_candidate_once, TempFile};
+
+    fn all(markov: &Markov, charset: &[char], min: u8, max: u8) -> Vec<String> {
+        let mut part = markov.part(charset, min, max, 0, 1);
+        let mut candidates = vec![];
+        let mut buf = String::new();
+        while part.next(&mut buf) {
+            candidates.push(buf.clone());
+        }
+        candidates
+    }
+
+    #[test]
+    fn test_train_save_load() {
+        let file = TempFile::new("markov", "cab\ncb\n# comment\n");
+        let model = MarkovModel::train(&file.wordlist()).unwrap();
+        assert_eq!(model.count(None, 'c'), 2);
+        assert_eq!(model.count(Some('c'), 'a'), 1);
+        assert_eq!(model.count(Some('a'), 'c'), 0);
+
+        model.save(file.path()).unwrap();
+        assert_eq!(MarkovModel::load(file.path()).unwrap(), model);
+
+        assert!(matches!(
+            MarkovModel::parse("^ 97 1\n97 x 1"),
+            Err(MarkovError::InvalidLine(2))
+        ));
+    }
+
+    #[test]
+    fn test_order() {
+        // c starts words, b follows c, a follows b
+        let model = MarkovModel::parse("^ 99 5\n99 98 3\n98 97 2\n97 99 1").unwrap();
+        let markov = Markov::new(model);
+        let charset = ['a', 'b', 'c'];
+        assert_eq!(markov.keyspace(&charset, 0, 2), Ok(13));
+        let candidates = all(&markov, &charset, 0, 2);
+        assert_eq!(candidates.len(), 13);
+        // 1, 6/8, 6/8 * 4/6
+        assert_eq!(candidates[..3], ["", "c", "cb"]);
+        // by the whole candidate, not by rank: 1/8 * 3/5 before 1/8 * 2/4
+        let position = |candidate| candidates.iter().position(|c| c == candidate);
+        assert!(position("ba") < position("ac"));
+
+        assert_eq!(markov.nth(&charset, 0, 2, 7), Some(candidates[7].clone()));
+        assert_eq!(markov.index_of(&charset, 0, 2, &candidates[7]), Some(7));
+        assert_eq!(markov.index_of(&charset, 0, 2, "cd"), None);
+
+        // 6/8 * 4/6 * 3/5 is the last one at least as likely
+        let markov = markov.with_threshold(0.2);
+        assert_eq!(markov.keyspace(&charset, 1, 3), Ok(3));
+        assert_eq!(all(&markov, &charset, 1, 3), ["c", "cb", "cba"]);
+        assert_eq!(markov.index_of(&charset, 1, 3, "ca"), None);
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let model = MarkovModel::parse("^ 99 5\n99 98 3\n98 97 2").unwrap();
+        let markov = Markov::new(model).with_threshold(0.01);
+        let charset = ['a', 'b', 'c'];
+        let expected = all(&markov, &charset, 1, 4);
+        assert_eq!(
+            expected.len() as u128,
+            markov.keyspace(&charset, 1, 4).unwrap()
+        );
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| {
+            markov.part(&charset, 1, 4, offset, step)
+        });
+    }
+}
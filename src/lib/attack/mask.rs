@@ -0,0 +1,172 @@
+use thiserror::Error;
+
+use crate::attack::indexed::{
+    digits_of, index_of_digits, radices_keyspace, Indexable, IndexedPart,
+};
+use crate::symbols::{KeyspaceOverflow, DIGITS, LATIN_LC, LATIN_UC};
+
+/// Errors while parsing a mask.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MaskError {
+    #[error("unknown class ?{0}")]
+    UnknownClass(char),
+    #[error("custom class ?{0} isn't defined")]
+    UndefinedClass(char),
+    #[error("custom class ?{0} is empty")]
+    EmptyClass(char),
+    #[error("the mask ends with a lone ?")]
+    TrailingQuestionMark,
+}
+
+/// A charset per position, e.g. `?u?l?l?l?l?l?d?d` for a capital, five lowercase letters
+/// & two digits.
+///
+/// - `?u`, `?l` & `?d` are `LATIN_UC`, `LATIN_LC` & `DIGITS`
+/// - `?1` to `?9` are the custom classes, in the order they are passed
+/// - `??` is a literal `?`, any other character is used as is
+///
+/// Candidates are enumerated like numbers, the last position changes fastest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    positions: Vec<Box<[char]>>,
+}
+
+impl Mask {
+    pub fn new(mask: &str) -> Result<Self, MaskError> {
+        Self::with_classes(mask, &[])
+    }
+
+    /// Parses the mask with custom classes, `classes[0]` is `?1`.
+    pub fn with_classes(mask: &str, classes: &[&[char]]) -> Result<Self, MaskError> {
+        let mut positions: Vec<Box<[char]>> = vec![];
+        let mut chars = mask.chars();
+        while let Some(char) = chars.next() {
+            if char != '?' {
+                positions.push(Box::from([char]));
+                continue;
+            }
+            let class = chars.next().ok_or(MaskError::TrailingQuestionMark)?;
+            let set: Box<[char]> = match class {
+                'u' => Box::from(LATIN_UC),
+                'l' => Box::from(LATIN_LC),
+                'd' => Box::from(DIGITS),
+                '?' => Box::from(['?']),
+                '1'..='9' => {
+                    let i = class as usize - '1' as usize;
+                    let set = classes.get(i).ok_or(MaskError::UndefinedClass(class))?;
+                    if set.is_empty() {
+                        return Err(MaskError::EmptyClass(class));
+                    }
+                    Box::from(*set)
+                }
+                class => return Err(MaskError::UnknownClass(class)),
+            };
+            positions.push(set);
+        }
+        Ok(Self { positions })
+    }
+
+    /// Number of positions, i.e. the length of every candidate.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn positions(&self) -> &[Box<[char]>] {
+        &self.positions
+    }
+
+    /// Exact number of candidates.
+    pub fn keyspace(&self) -> Result<u128, KeyspaceOverflow> {
+        radices_keyspace(&self.radices())
+    }
+
+    /// Writes the candidate at `index` into `buf`. `index` must be below the keyspace.
+    pub fn nth(&self, index: u128, buf: &mut String) {
+        buf.clear();
+        self.write(&digits_of(&self.radices(), index), buf);
+    }
+
+    /// The index of `candidate`, None if the mask doesn't produce it.
+    pub fn index_of(&self, candidate: &str) -> Option<u128> {
+        index_of_digits(&self.radices(), &self.parse(candidate)?)
+    }
+
+    /// The digits of `candidate`, i.e. the position of each char in its set.
+    pub(crate) fn parse(&self, candidate: &str) -> Option<Vec<usize>> {
+        if candidate.chars().count() != self.len() {
+            return None;
+        }
+        self.positions
+            .iter()
+            .zip(candidate.chars())
+            .map(|(set, char)| set.iter().position(|c| *c == char))
+            .collect()
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(&self, offset: u128, step: u128) -> IndexedPart<Mask> {
+        IndexedPart::new(self.clone(), offset, step)
+    }
+}
+
+impl Indexable for Mask {
+    fn radices(&self) -> Vec<usize> {
+        self.positions.iter().map(|set| set.len()).collect()
+    }
+
+    fn write(&self, digits: &[usize], buf: &mut String) {
+        for (set, digit) in self.positions.iter().zip(digits) {
+            buf.push(set[*digit]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_mask {
+    use super::*;
+    use crate::attack::tests_attack::assert_parts_cover_every_candidate_once;
+
+    fn all(mask: &Mask) -> Vec<String> {
+        let mut buf = String::new();
+        (0..mask.keyspace().unwrap())
+            .map(|i| {
+                mask.nth(i, &mut buf);
+                buf.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let mask = Mask::new("?u?l?l?l?l?l?d?d").unwrap();
+        assert_eq!(mask.len(), 8);
+        assert_eq!(mask.keyspace(), Ok(26 * 26u128.pow(5) * 100));
+        let mask = Mask::new(&"?d".repeat(39)).unwrap();
+        assert_eq!(mask.keyspace(), Err(KeyspaceOverflow));
+
+        let mask = Mask::with_classes("x??-?1?d", &[&['a', 'b']]).unwrap();
+        assert_eq!(mask.keyspace(), Ok(20));
+        assert_eq!(all(&mask)[..3], ["x?-a0", "x?-a1", "x?-a2"]);
+        assert_eq!(all(&mask)[19], "x?-b9");
+
+        assert_eq!(Mask::new("?x"), Err(MaskError::UnknownClass('x')));
+        assert_eq!(Mask::new("?2"), Err(MaskError::UndefinedClass('2')));
+        assert_eq!(
+            Mask::with_classes("?1", &[&[]]),
+            Err(MaskError::EmptyClass('1'))
+        );
+        assert_eq!(Mask::new("ab?"), Err(MaskError::TrailingQuestionMark));
+        assert_eq!(Mask::new("").unwrap().keyspace(), Ok(1));
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let mask = Mask::with_classes("?1?d?1", &[&['a', 'b', 'c']]).unwrap();
+        let expected = all(&mask);
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| mask.part(offset, step));
+    }
+}
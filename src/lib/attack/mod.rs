@@ -0,0 +1,372 @@
+mod brute_force;
+mod combinator;
+mod hybrid;
+mod indexed;
+mod markov;
+mod mask;
+mod passphrase;
+mod pcfg;
+mod rules;
+mod wordlist;
+
+use std::{fmt, io};
+
+use crate::jni::indices::{indices_at, indices_to_index, indices_to_string};
+use crate::symbols::{combination_count, KeyspaceOverflow};
+
+pub(crate) use brute_force::BruteForce;
+pub use combinator::{CaseStyle, Combinator};
+pub use hybrid::Hybrid;
+pub use markov::{Markov, MarkovError, MarkovModel};
+pub use mask::{Mask, MaskError};
+pub use passphrase::Passphrase;
+pub use pcfg::{Pcfg, PcfgError, PcfgModel};
+pub use rules::{Rule, RuleError, RuleStats, Rules};
+pub use wordlist::Wordlist;
+pub(crate) use wordlist::WordlistPart;
+
+/// How far `Attack::nth_candidate` replays the order of a Markov model or a PCFG,
+/// a few seconds of popping their priority queue.
+pub const MAX_REPLAYED: u128 = 1 << 24;
+
+/// Where the candidates come from.
+#[derive(Debug, Clone, Default)]
+pub enum Attack {
+    /// Every combination of the param's charset between its min & max length.
+    #[default]
+    BruteForce,
+    /// Every line of a wordlist, streamed from disk.
+    Wordlist(Wordlist),
+    /// Every candidate of a mask, e.g. `?u?l?l?d?d`.
+    Mask(Mask),
+    /// Every word of a wordlist with every candidate of a mask before or after it.
+    Hybrid(Hybrid),
+    /// Every combination of one word from each of several wordlists.
+    Combinator(Combinator),
+    /// Like `BruteForce`, but the most likely candidates of a Markov model first.
+    Markov(Markov),
+    /// The candidates of a PCFG model, most likely first.
+    Pcfg(Pcfg),
+    /// Every sequence of words of a token list, e.g. diceware passphrases.
+    Passphrase(Passphrase),
+}
+
+impl Attack {
+    /// Exact number of candidates, the charset & lengths are only used by the brute force
+    /// attacks. None for wordlists, as their lines aren't counted upfront.
+    pub fn keyspace(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+    ) -> Result<Option<u128>, KeyspaceOverflow> {
+        let keyspace = match self {
+            Attack::BruteForce => combination_count(charset, min_length, max_length)?,
+            Attack::Wordlist(_) => return Ok(None),
+            Attack::Mask(mask) => mask.keyspace()?,
+            Attack::Hybrid(hybrid) => hybrid.keyspace()?,
+            Attack::Combinator(combinator) => combinator.keyspace()?,
+            Attack::Markov(markov) => markov.keyspace(charset, min_length, max_length)?,
+            Attack::Pcfg(pcfg) => pcfg.keyspace()?,
+            Attack::Passphrase(passphrase) => passphrase.keyspace()?,
+        };
+        Ok(Some(keyspace))
+    }
+
+    /// The candidate at index `n` of the attack's order, None past the last one.
+    /// Most attacks jump right to it, e.g. to resume a run or to split it between machines.
+    /// Wordlists read their lines from the start. Markov models & PCFGs pop their priority
+    /// queue from the start, which costs O(n log n), so they refuse an `n` of `MAX_REPLAYED`
+    /// or more with `InvalidInput`. Otherwise fails only if the wordlist can't be read.
+    pub fn nth_candidate(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+        n: u128,
+    ) -> io::Result<Option<String>> {
+        let in_keyspace = |keyspace: Result<u128, KeyspaceOverflow>| {
+            // an index can't be past a keyspace that doesn't even fit into a u128
+            keyspace.map_or(true, |keyspace| n < keyspace)
+        };
+        let mut buf = String::new();
+        match self {
+            Attack::BruteForce => {
+                let Some(indices) = indices_at(charset, max_length, min_length, n) else {
+                    return Ok(None);
+                };
+                indices_to_string(&mut buf, charset, &indices);
+            }
+            Attack::Wordlist(wordlist) => {
+                return wordlist.nth(n);
+            }
+            Attack::Mask(mask) if in_keyspace(mask.keyspace()) => mask.nth(n, &mut buf),
+            Attack::Hybrid(hybrid) if in_keyspace(hybrid.keyspace()) => hybrid.nth(n, &mut buf),
+            Attack::Combinator(combinator) if in_keyspace(combinator.keyspace()) => {
+                combinator.nth(n, &mut buf)
+            }
+            Attack::Markov(_) | Attack::Pcfg(_) if n >= MAX_REPLAYED => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("can't replay the first {} candidates of the queue", n),
+                ));
+            }
+            Attack::Markov(markov) => return Ok(markov.nth(charset, min_length, max_length, n)),
+            Attack::Pcfg(pcfg) => return Ok(pcfg.nth(n)),
+            Attack::Passphrase(passphrase) => return Ok(passphrase.nth(n)),
+            _ => return Ok(None),
+        }
+        Ok(Some(buf))
+    }
+
+    /// Where `candidate` is in the attack's order, the inverse of `nth_candidate`.
+    /// None if the attack never tries it, the first index if it tries it more than once.
+    /// Wordlists, Markov models & PCFGs walk their order from the start until they meet it,
+    /// for the latter two that costs O(n log n). Fails only if the wordlist can't be read.
+    pub fn candidate_index(
+        &self,
+        charset: &[char],
+        min_length: u8,
+        max_length: u8,
+        candidate: &str,
+    ) -> io::Result<Option<u128>> {
+        let index = match self {
+            Attack::BruteForce => brute_force_index(charset, min_length, max_length, candidate),
+            Attack::Wordlist(wordlist) => return wordlist.index_of(candidate),
+            Attack::Mask(mask) => mask.index_of(candidate),
+            Attack::Hybrid(hybrid) => hybrid.index_of(candidate),
+            Attack::Combinator(combinator) => combinator.index_of(candidate),
+            Attack::Markov(markov) => markov.index_of(charset, min_length, max_length, candidate),
+            Attack::Pcfg(pcfg) => pcfg.index_of(candidate),
+            Attack::Passphrase(passphrase) => passphrase.index_of(candidate),
+        };
+        Ok(index)
+    }
+}
+
+/// The index of `candidate` in the brute force order, see `Attack::candidate_index`.
+fn brute_force_index(
+    charset: &[char],
+    min_length: u8,
+    max_length: u8,
+    candidate: &str,
+) -> Option<u128> {
+    let length = candidate.chars().count();
+    if length < min_length.into() || length > max_length.into() {
+        return None;
+    }
+    // right aligned, like the indices while cracking
+    let mut indices = vec![-1; max_length.into()];
+    let first = indices.len() - length;
+    for (slot, char) in indices[first..].iter_mut().zip(candidate.chars()) {
+        *slot = charset.iter().position(|c| *c == char)? as isize;
+    }
+    indices_to_index(charset, min_length, &indices)
+}
+
+/// The candidates of a single worker.
+pub(crate) trait Candidates: Send {
+    /// Writes the next candidate into `buf`. Returns false once the worker's share is done.
+    fn next(&mut self, buf: &mut String) -> bool;
+
+    /// The oracle judged the last candidate.
+    fn checked(&mut self) {}
+
+    /// The last candidate was correct.
+    fn hit(&mut self) {}
+
+    /// Per rule statistics, for sources that mangle words with rules.
+    fn rule_stats(&self) -> Vec<RuleStats> {
+        vec![]
+    }
+
+    /// Why `next` returned false before the worker's share was done,
+    /// e.g. the wordlist became unreadable.
+    fn take_error(&mut self) -> Option<io::Error> {
+        None
+    }
+
+    fn progress(&self) -> Progress;
+}
+
+/// How far a worker got through its share, or all workers of a crack together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    Candidates {
+        done: u128,
+        total: u128,
+    },
+    /// For sources that are streamed, where the number of candidates isn't known upfront.
+    Bytes {
+        done: u64,
+        total: u64,
+        lines: u64,
+    },
+}
+
+impl Progress {
+    pub fn percent(&self) -> f32 {
+        let (done, total) = match *self {
+            Progress::Candidates { done, total } => (done as f32, total as f32),
+            Progress::Bytes { done, total, .. } => (done as f32, total as f32),
+        };
+        if total == 0.0 {
+            100.0
+        } else {
+            done / total * 100.0
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>6.2}%", self.percent())?;
+        if let Progress::Bytes { done, total, lines } = self {
+            write!(f, " ({} lines, {}/{} bytes)", lines, done, total)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests_attack {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A file in the temp dir that's removed on drop, even if an assertion fails.
+    /// Every file gets its own name, so that tests running at the same time can't
+    /// overwrite each other's.
+    pub(crate) struct TempFile(PathBuf);
+
+    impl TempFile {
+        pub fn new(name: &str, content: impl AsRef<[u8]>) -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let count = COUNT.fetch_add(1, Ordering::Relaxed);
+            let path = std::env::temp_dir().join(format!(
+                "tokio_pw_{}_{}_{}",
+                name,
+                std::process::id(),
+                count
+            ));
+            fs::write(&path, content).unwrap();
+            Self(path)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.0
+        }
+
+        pub fn wordlist(&self) -> Wordlist {
+            Wordlist::new(&self.0)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            // the test may have removed it already
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Asserts that for every step, the parts `part(offset, step)` interleave to `expected`,
+    /// i.e. that they cover every candidate once, & that every part ends at 100% progress.
+    pub(crate) fn assert_parts_cover_every_candidate_once<C: Candidates>(
+        expected: &[String],
+        part: impl Fn(u128, u128) -> C,
+    ) {
+        for step in [1u128, 2, 7, 100] {
+            let mut candidates = vec![String::new(); expected.len()];
+            let mut buf = String::new();
+            for offset in 0..step {
+                let mut part = part(offset, step);
+                let mut index = offset;
+                while part.next(&mut buf) {
+                    candidates[index as usize] = buf.clone();
+                    index += step;
+                }
+                assert_eq!(part.progress().percent(), 100.0);
+            }
+            assert_eq!(candidates, expected, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_nth_candidate_and_candidate_index() {
+        let file = TempFile::new("attack", "foo\n# comment\nbar\nfoo\n");
+        let wordlist = file.wordlist();
+        let mask = Mask::new("?d?l").unwrap();
+        let markov = MarkovModel::parse("^ 99 5\n99 98 3\n98 97 2\n97 99 1").unwrap();
+        let pcfg = PcfgModel::parse("structure L1D1 3\nterminal L1 2 a\nterminal D1 3 1").unwrap();
+        let attacks = [
+            Attack::BruteForce,
+            Attack::Wordlist(wordlist.clone()),
+            Attack::Mask(mask.clone()),
+            Attack::Hybrid(Hybrid::mask_wordlist(mask, &wordlist).unwrap()),
+            Attack::Combinator(
+                Combinator::new(&[wordlist.clone(), wordlist.clone()])
+                    .unwrap()
+                    .with_separator("-"),
+            ),
+            Attack::Markov(Markov::new(markov)),
+            Attack::Pcfg(Pcfg::new(&pcfg)),
+            Attack::Passphrase(
+                Passphrase::from_wordlist(&wordlist, 1, 2)
+                    .unwrap()
+                    .with_case_styles(&[CaseStyle::AsIs, CaseStyle::Upper]),
+            ),
+        ];
+        let charset = ['a', 'b', 'c'];
+        for attack in &attacks {
+            let keyspace = attack.keyspace(&charset, 1, 3).unwrap().unwrap_or(3);
+            let mut seen = vec![];
+            for n in 0..keyspace {
+                let candidate = attack.nth_candidate(&charset, 1, 3, n).unwrap().unwrap();
+                let index = attack
+                    .candidate_index(&charset, 1, 3, &candidate)
+                    .unwrap()
+                    .unwrap();
+                // duplicates, e.g. the second foo of the wordlist, are found at their first index
+                let first = seen
+                    .iter()
+                    .position(|c| *c == candidate)
+                    .unwrap_or(seen.len());
+                assert_eq!(index, first as u128, "{:?} {}", attack, candidate);
+                seen.push(candidate);
+            }
+            assert_eq!(
+                attack.nth_candidate(&charset, 1, 3, keyspace).unwrap(),
+                None
+            );
+            assert_eq!(attack.candidate_index(&charset, 1, 3, "xyz").unwrap(), None);
+        }
+        drop(file);
+
+        let brute_force = Attack::BruteForce;
+        assert_eq!(
+            brute_force.nth_candidate(&charset, 1, 3, 3).unwrap(),
+            Some("aa".into())
+        );
+        assert_eq!(
+            brute_force.candidate_index(&charset, 1, 3, "aa").unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            brute_force.candidate_index(&charset, 1, 3, "cccc").unwrap(),
+            None
+        );
+
+        // a PCFG would pop its queue for ages
+        let error = attacks[6]
+            .nth_candidate(&charset, 1, 3, MAX_REPLAYED)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        // a wordlist that can't be read is an error, not a panic
+        let attack = Attack::Wordlist(wordlist);
+        assert!(attack.nth_candidate(&charset, 1, 3, 0).is_err());
+        assert!(attack.candidate_index(&charset, 1, 3, "foo").is_err());
+    }
+}
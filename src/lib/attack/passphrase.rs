@@ -0,0 +1,278 @@
+use std::{io, sync::Arc};
+
+use crate::attack::indexed::{index_of_digits, lengths_keyspace, Lengths};
+use crate::attack::{Candidates, CaseStyle, Progress, Wordlist};
+use crate::symbols::KeyspaceOverflow;
+
+/// Brute force with words instead of chars: every sequence of `min_words` to `max_words`
+/// tokens, joined by a separator, e.g. `correct horse battery staple` from a diceware list.
+///
+/// Like the brute force indices, every number of words is enumerated like a number with one
+/// digit per word, fewest words first. The case style is the last digit, so every passphrase
+/// is tried in all styles before the next one.
+#[derive(Debug, Clone)]
+pub struct Passphrase {
+    // shared by the parts of all workers
+    tokens: Arc<[String]>,
+    min_words: usize,
+    max_words: usize,
+    separator: String,
+    styles: Vec<CaseStyle>,
+}
+
+impl Passphrase {
+    /// The words are separated by a space by default.
+    pub fn new(tokens: Vec<String>, min_words: usize, max_words: usize) -> Self {
+        if min_words > max_words {
+            panic!("min words must be <= max words")
+        }
+        Self {
+            tokens: tokens.into(),
+            min_words,
+            max_words,
+            separator: " ".into(),
+            styles: vec![CaseStyle::AsIs],
+        }
+    }
+
+    /// One token per line. Only the last field of a line is used, so that diceware lists
+    /// with the dice rolls in front, e.g. `11111 abacus`, work as they are.
+    pub fn from_wordlist(
+        wordlist: &Wordlist,
+        min_words: usize,
+        max_words: usize,
+    ) -> io::Result<Self> {
+        let tokens = wordlist
+            .words()?
+            .into_iter()
+            .filter_map(|line| line.split_whitespace().last().map(String::from))
+            .collect();
+        Ok(Self::new(tokens, min_words, max_words))
+    }
+
+    /// Put between the words, may be empty.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Tries every passphrase with all of its words in each of the styles.
+    /// Panics if there's no style.
+    pub fn with_case_styles(mut self, styles: &[CaseStyle]) -> Self {
+        assert!(
+            !styles.is_empty(),
+            "A passphrase needs at least one case style"
+        );
+        self.styles = styles.to_vec();
+        self
+    }
+
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    pub fn min_words(&self) -> usize {
+        self.min_words
+    }
+
+    pub fn max_words(&self) -> usize {
+        self.max_words
+    }
+
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn case_styles(&self) -> &[CaseStyle] {
+        &self.styles
+    }
+
+    /// Exact number of candidates.
+    pub fn keyspace(&self) -> Result<u128, KeyspaceOverflow> {
+        lengths_keyspace(
+            self.tokens.len(),
+            &[self.styles.len()],
+            self.min_words,
+            self.max_words,
+        )
+    }
+
+    /// The candidate at `index`, None past the last one.
+    pub fn nth(&self, index: u128) -> Option<String> {
+        let lengths = self.lengths(index, 1);
+        let mut buf = String::new();
+        self.write(lengths.digits()?, &mut buf);
+        Some(buf)
+    }
+
+    /// The index of `candidate`, None if the passphrase attack doesn't produce it.
+    /// If it does more than once, e.g. with tokens that contain the separator, the first one.
+    pub fn index_of(&self, candidate: &str) -> Option<u128> {
+        // fewer words come first, so the first length with a match has the smallest index
+        for length in self.min_words..=self.max_words {
+            let mut best = None;
+            for (s, style) in self.styles.iter().enumerate() {
+                let mut words = vec![];
+                if self.find(candidate, *style, length, &mut words) {
+                    words.push(s);
+                    let mut radices = vec![self.tokens.len(); length];
+                    radices.push(self.styles.len());
+                    let index = index_of_digits(&radices, &words)?;
+                    best = Some(best.map_or(index, |best: u128| best.min(index)));
+                }
+            }
+            if let Some(index) = best {
+                let shorter = if length > self.min_words {
+                    let styles = [self.styles.len()];
+                    lengths_keyspace(self.tokens.len(), &styles, self.min_words, length - 1).ok()?
+                } else {
+                    0
+                };
+                return shorter.checked_add(index);
+            }
+        }
+        None
+    }
+
+    /// Depth first search for the `length - words.len()` tokens in `style` that make up `rest`,
+    /// the smallest tokens first.
+    fn find(&self, rest: &str, style: CaseStyle, length: usize, words: &mut Vec<usize>) -> bool {
+        if words.len() == length {
+            return rest.is_empty();
+        }
+        let rest = if words.is_empty() {
+            rest
+        } else {
+            match rest.strip_prefix(self.separator.as_str()) {
+                Some(rest) => rest,
+                None => return false,
+            }
+        };
+
+        let mut styled = String::new();
+        for (t, token) in self.tokens.iter().enumerate() {
+            styled.clear();
+            style.write(token, &mut styled);
+            if let Some(rest) = rest.strip_prefix(styled.as_str()) {
+                words.push(t);
+                if self.find(rest, style, length, words) {
+                    return true;
+                }
+                words.pop();
+            }
+        }
+        false
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(&self, offset: u128, step: u128) -> PassphrasePart {
+        PassphrasePart {
+            passphrase: self.clone(),
+            lengths: self.lengths(offset, step),
+            done: 0,
+            total: self
+                .keyspace()
+                .unwrap_or(u128::MAX)
+                .saturating_sub(offset)
+                .div_ceil(step),
+        }
+    }
+
+    fn lengths(&self, offset: u128, step: u128) -> Lengths {
+        Lengths::new(
+            self.tokens.len(),
+            vec![self.styles.len()],
+            self.min_words,
+            self.max_words,
+            offset,
+            step,
+        )
+    }
+
+    /// The digits are the tokens, followed by the style.
+    fn write(&self, digits: &[usize], buf: &mut String) {
+        let (words, style) = digits.split_at(digits.len() - 1);
+        let style = self.styles[style[0]];
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                buf.push_str(&self.separator);
+            }
+            style.write(&self.tokens[*word], buf);
+        }
+    }
+}
+
+/// The share of a passphrase attack's candidates of one worker.
+pub(crate) struct PassphrasePart {
+    passphrase: Passphrase,
+    lengths: Lengths,
+    done: u128,
+    total: u128,
+}
+
+impl Candidates for PassphrasePart {
+    fn next(&mut self, buf: &mut String) -> bool {
+        let Some(digits) = self.lengths.digits() else {
+            return false;
+        };
+        buf.clear();
+        self.passphrase.write(digits, buf);
+        self.done += 1;
+        self.lengths.advance();
+        true
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.done,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_passphrase {
+    use super::*;
+    use crate::attack::tests_attack::{assert_parts_cover_every_candidate_once, TempFile};
+
+    fn all(passphrase: &Passphrase) -> Vec<String> {
+        (0..passphrase.keyspace().unwrap())
+            .map(|i| passphrase.nth(i).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_order() {
+        let file = TempFile::new("dice", "11111\tcorrect\n11112\thorse\n11113\tstaple\n");
+        let passphrase = Passphrase::from_wordlist(&file.wordlist(), 2, 3)
+            .unwrap()
+            .with_separator("-");
+        assert_eq!(passphrase.tokens(), ["correct", "horse", "staple"]);
+        assert_eq!(passphrase.keyspace(), Ok(9 + 27));
+        let candidates = all(&passphrase);
+        assert_eq!(candidates[..2], ["correct-correct", "correct-horse"]);
+        assert_eq!(candidates[9], "correct-correct-correct");
+        assert_eq!(candidates[35], "staple-staple-staple");
+        assert_eq!(passphrase.nth(36), None);
+
+        let tokens = vec!["correct".into(), "Horse".into()];
+        let passphrase = Passphrase::new(tokens, 1, 2)
+            .with_separator("")
+            .with_case_styles(&[CaseStyle::Capitalized, CaseStyle::Upper]);
+        let candidates = all(&passphrase);
+        assert_eq!(candidates[..4], ["Correct", "CORRECT", "Horse", "HORSE"]);
+        assert_eq!(candidates[6..8], ["CorrectHorse", "CORRECTHORSE"]);
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let tokens = vec!["a".into(), "bb".into(), "ccc".into()];
+        let passphrase =
+            Passphrase::new(tokens, 0, 3).with_case_styles(&[CaseStyle::AsIs, CaseStyle::Upper]);
+        let expected = all(&passphrase);
+        assert_eq!(expected.len(), 2 * (1 + 3 + 9 + 27));
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| {
+            passphrase.part(offset, step)
+        });
+    }
+}
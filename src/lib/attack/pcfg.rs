@@ -0,0 +1,492 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap},
+    fmt, fs, io,
+    path::Path,
+    sync::Arc,
+};
+
+use thiserror::Error;
+
+use crate::attack::indexed::radices_keyspace;
+use crate::attack::{Candidates, Progress, Wordlist};
+use crate::symbols::KeyspaceOverflow;
+
+/// Written at the top of every model file.
+const HEADER: &str = "# tokio_pw pcfg model: `structure <structure> <count>` & \
+                      `terminal <segment> <count> <terminal>`";
+
+/// Errors while loading a model file.
+#[derive(Debug, Error)]
+pub enum PcfgError {
+    #[error("line {0} is neither a structure nor a terminal")]
+    InvalidLine(usize),
+    #[error("reading the model file failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// The class of a char in a structure: `L`etter, `D`igit or `S`ymbol, i.e. anything else.
+fn class(char: char) -> char {
+    if char.is_alphabetic() {
+        'L'
+    } else if char.is_ascii_digit() {
+        'D'
+    } else {
+        'S'
+    }
+}
+
+/// Splits a word into runs of the same class, e.g. `pass12!` into `pass`, `12` & `!`.
+fn segments(word: &str) -> Vec<&str> {
+    let mut segments = vec![];
+    let mut start = 0;
+    let mut current = None;
+    for (i, char) in word.char_indices() {
+        let class = class(char);
+        if current.is_some_and(|current| current != class) {
+            segments.push(&word[start..i]);
+            start = i;
+        }
+        current = Some(class);
+    }
+    if start < word.len() {
+        segments.push(&word[start..]);
+    }
+    segments
+}
+
+/// The name of a segment, its class & its length in chars, e.g. `L4` for `pass`.
+fn segment_name(segment: &str) -> String {
+    let first = segment.chars().next().unwrap();
+    format!("{}{}", class(first), segment.chars().count())
+}
+
+/// How often each structure, e.g. `L4D2S1` for `pass12!`, & each terminal of each segment,
+/// e.g. `pass` for `L4`, occurred in a corpus of real passwords.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PcfgModel {
+    structures: BTreeMap<String, u64>,
+    terminals: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+impl PcfgModel {
+    /// Counts every word of the corpus, comments & rules work like in a wordlist attack.
+    pub fn train(corpus: &Wordlist) -> io::Result<Self> {
+        let mut model = Self::default();
+        let mut part = corpus.open_part(0, 1)?;
+        let mut word = String::new();
+        while part.next(&mut word) {
+            let mut structure = String::new();
+            for segment in segments(&word) {
+                let name = segment_name(segment);
+                structure.push_str(&name);
+                *model
+                    .terminals
+                    .entry(name)
+                    .or_default()
+                    .entry(segment.into())
+                    .or_insert(0) += 1;
+            }
+            *model.structures.entry(structure).or_insert(0) += 1;
+        }
+        part.take_error()?;
+        Ok(model)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PcfgError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses the content of a model file. Empty lines & lines starting with `#` are skipped.
+    pub fn parse(model: &str) -> Result<Self, PcfgError> {
+        let mut parsed = Self::default();
+        for (i, line) in model.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || PcfgError::InvalidLine(i + 1);
+            // the terminal comes last, as it may contain spaces
+            let fields = line.splitn(4, ' ').collect::<Vec<_>>();
+            match fields[..] {
+                ["structure", structure, count] => {
+                    let count = count.parse().map_err(|_| invalid())?;
+                    parsed.structures.insert(structure.into(), count);
+                }
+                ["terminal", segment, count, terminal] => {
+                    let count = count.parse().map_err(|_| invalid())?;
+                    parsed
+                        .terminals
+                        .entry(segment.into())
+                        .or_default()
+                        .insert(terminal.into(), count);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// How often the structure, e.g. `L4D2`, occurred.
+    pub fn structure_count(&self, structure: &str) -> u64 {
+        self.structures.get(structure).copied().unwrap_or(0)
+    }
+
+    /// How often `terminal` filled the segment, e.g. `pass` the segment `L4`.
+    pub fn terminal_count(&self, segment: &str, terminal: &str) -> u64 {
+        self.terminals
+            .get(segment)
+            .and_then(|terminals| terminals.get(terminal))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for PcfgModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for (structure, count) in &self.structures {
+            writeln!(f, "structure {} {}", structure, count)?;
+        }
+        for (segment, terminals) in &self.terminals {
+            for (terminal, count) in terminals {
+                writeln!(f, "terminal {} {} {}", segment, count, terminal)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A structure with the terminals of each of its segments, most likely first.
+#[derive(Debug)]
+struct Structure {
+    probability: f64,
+    segments: Vec<Arc<[(String, f64)]>>,
+}
+
+/// Candidates of a PCFG model in order of probability, e.g. `password1` before `Password!`.
+/// The probability of a candidate is the one of its structure times those of its terminals.
+///
+/// The order only exists through a priority queue, so a crack runs it on one thread
+/// that feeds all workers.
+#[derive(Debug, Clone)]
+pub struct Pcfg {
+    structures: Arc<[Structure]>,
+}
+
+impl Pcfg {
+    /// Structures with a segment without terminals, e.g. from an edited model file, are dropped.
+    pub fn new(model: &PcfgModel) -> Self {
+        let probabilities = |counts: &BTreeMap<String, u64>| {
+            let total = counts.values().sum::<u64>() as f64;
+            let mut probabilities = counts
+                .iter()
+                .map(|(terminal, count)| (terminal.clone(), *count as f64 / total))
+                .collect::<Vec<_>>();
+            // ties keep the order of the model
+            probabilities.sort_by(|a, b| b.1.total_cmp(&a.1));
+            probabilities
+        };
+        let terminals = model
+            .terminals
+            .iter()
+            .map(|(segment, terminals)| (segment.as_str(), probabilities(terminals).into()))
+            .collect::<BTreeMap<_, Arc<[(String, f64)]>>>();
+
+        let structures = probabilities(&model.structures)
+            .into_iter()
+            .filter_map(|(structure, probability)| {
+                let segments = split_structure(&structure)
+                    .map(|segment| terminals.get(segment).cloned())
+                    .collect::<Option<Vec<_>>>()?;
+                Some(Structure {
+                    probability,
+                    segments,
+                })
+            })
+            .filter(|structure| !structure.segments.is_empty())
+            .collect::<Vec<_>>();
+        Self {
+            structures: structures.into(),
+        }
+    }
+
+    /// Exact number of candidates, the sum over all structures of the product
+    /// of the number of terminals of their segments.
+    pub fn keyspace(&self) -> Result<u128, KeyspaceOverflow> {
+        self.structures
+            .iter()
+            .try_fold(0u128, |keyspace, structure| {
+                let terminals = structure.segments.iter().map(|terminals| terminals.len());
+                let terminals = radices_keyspace(&terminals.collect::<Vec<_>>())?;
+                keyspace.checked_add(terminals).ok_or(KeyspaceOverflow)
+            })
+    }
+
+    /// The candidate at `index`, None past the last one.
+    /// Walks the queue from the start, as the order only exists through it.
+    pub fn nth(&self, index: u128) -> Option<String> {
+        // a keyspace that doesn't fit into a u128 has room for any index
+        if self.keyspace().is_ok_and(|keyspace| index >= keyspace) {
+            return None;
+        }
+        let mut buf = String::new();
+        self.part(index, 1).next(&mut buf).then_some(buf)
+    }
+
+    /// The index of `candidate`, None if the model doesn't produce it.
+    /// Walks the queue from the start, as the order only exists through it, but only
+    /// if a structure has a terminal for every segment of the candidate. Terminals of an
+    /// edited model file that span several segments, e.g. `a1` for `L2`, aren't found.
+    pub fn index_of(&self, candidate: &str) -> Option<u128> {
+        let segments = segments(candidate);
+        let produced = self.structures.iter().any(|structure| {
+            structure.segments.len() == segments.len()
+                && structure
+                    .segments
+                    .iter()
+                    .zip(&segments)
+                    .all(|(terminals, segment)| terminals.iter().any(|(t, _)| t == segment))
+        });
+        if !produced {
+            return None;
+        }
+        let mut part = self.part(0, 1);
+        let mut buf = String::new();
+        let mut index = 0;
+        while part.next(&mut buf) {
+            if buf == candidate {
+                return Some(index);
+            }
+            index += 1;
+        }
+        None
+    }
+
+    /// Every `step`th candidate, starting at `offset`.
+    pub(crate) fn part(&self, offset: u128, step: u128) -> PcfgPart {
+        let mut queue = BinaryHeap::new();
+        for (i, structure) in self.structures.iter().enumerate() {
+            let terminals = vec![0; structure.segments.len()];
+            let probability = self.probability(i, &terminals);
+            queue.push(Node {
+                probability,
+                structure: i,
+                terminals,
+                pivot: 0,
+            });
+        }
+        PcfgPart {
+            pcfg: self.clone(),
+            queue,
+            index: 0,
+            offset,
+            step,
+            done: 0,
+            total: self
+                .keyspace()
+                .unwrap_or(u128::MAX)
+                .saturating_sub(offset)
+                .div_ceil(step),
+        }
+    }
+
+    fn probability(&self, structure: usize, terminals: &[usize]) -> f64 {
+        let structure = &self.structures[structure];
+        let segments = structure.segments.iter().zip(terminals);
+        segments.fold(structure.probability, |probability, (segment, terminal)| {
+            probability * segment[*terminal].1
+        })
+    }
+}
+
+/// Splits e.g. `L4D2S1` into `L4`, `D2` & `S1`.
+fn split_structure(structure: &str) -> impl Iterator<Item = &str> {
+    let starts = structure
+        .char_indices()
+        .filter(|(_, char)| char.is_ascii_alphabetic())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let ends = starts
+        .iter()
+        .skip(1)
+        .copied()
+        .chain([structure.len()])
+        .collect::<Vec<_>>();
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(move |(start, end)| &structure[start..end])
+}
+
+/// A candidate in the queue: the structure & the index of the terminal of each segment.
+#[derive(Debug)]
+struct Node {
+    probability: f64,
+    structure: usize,
+    terminals: Vec<usize>,
+    // only segments from here on are advanced, so that every node is queued once
+    pivot: usize,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the most likely first, ties in the order of the structures & terminals
+        self.probability
+            .total_cmp(&other.probability)
+            .then_with(|| other.structure.cmp(&self.structure))
+            .then_with(|| other.terminals.cmp(&self.terminals))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+/// The share of a PCFG's candidates of one worker.
+pub(crate) struct PcfgPart {
+    pcfg: Pcfg,
+    queue: BinaryHeap<Node>,
+    // the index of the next candidate of the queue
+    index: u128,
+    offset: u128,
+    step: u128,
+    done: u128,
+    total: u128,
+}
+
+impl PcfgPart {
+    /// Takes the most likely node & queues its successors, the nodes with one terminal
+    /// at or after the pivot advanced to the next less likely one.
+    fn pop(&mut self) -> Option<Node> {
+        let node = self.queue.pop()?;
+        let segments = &self.pcfg.structures[node.structure].segments;
+        for i in node.pivot..segments.len() {
+            if node.terminals[i] + 1 < segments[i].len() {
+                let mut terminals = node.terminals.clone();
+                terminals[i] += 1;
+                self.queue.push(Node {
+                    probability: self.pcfg.probability(node.structure, &terminals),
+                    structure: node.structure,
+                    terminals,
+                    pivot: i,
+                });
+            }
+        }
+        Some(node)
+    }
+}
+
+impl Candidates for PcfgPart {
+    fn next(&mut self, buf: &mut String) -> bool {
+        loop {
+            let Some(node) = self.pop() else {
+                return false;
+            };
+            let index = self.index;
+            self.index += 1;
+            if index < self.offset || !(index - self.offset).is_multiple_of(self.step) {
+                continue;
+            }
+
+            buf.clear();
+            let segments = &self.pcfg.structures[node.structure].segments;
+            for (segment, terminal) in segments.iter().zip(&node.terminals) {
+                buf.push_str(&segment[*terminal].0);
+            }
+            self.done += 1;
+            return true;
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.done,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_pcfg {
+    use super::*;
+    use crate::attack::tests_attack::{assert_parts_cover_every_candidate_once, TempFile};
+
+    fn all(pcfg: &Pcfg) -> Vec<String> {
+        let mut part = pcfg.part(0, 1);
+        let mut candidates = vec![];
+        let mut buf = String::new();
+        while part.next(&mut buf) {
+            candidates.push(buf.clone());
+        }
+        candidates
+    }
+
+    #[test]
+    fn test_train_save_load() {
+        let file = TempFile::new("pcfg", "pass12!\nabcd99\nab cd\n# comment\n");
+        let model = PcfgModel::train(&file.wordlist()).unwrap();
+        assert_eq!(model.structure_count("L4D2S1"), 1);
+        assert_eq!(model.structure_count("L4D2"), 1);
+        assert_eq!(model.structure_count("L2S1L2"), 1);
+        assert_eq!(model.terminal_count("L4", "abcd"), 1);
+        assert_eq!(model.terminal_count("S1", " "), 1);
+
+        model.save(file.path()).unwrap();
+        assert_eq!(PcfgModel::load(file.path()).unwrap(), model);
+
+        assert!(matches!(
+            PcfgModel::parse("structure L1 1\nterminal L1 x"),
+            Err(PcfgError::InvalidLine(2))
+        ));
+    }
+
+    #[test]
+    fn test_order() {
+        let model = PcfgModel::parse(
+            "structure L1D1 3\nstructure D1 1\n\
+             terminal L1 2 a\nterminal L1 1 b\n\
+             terminal D1 3 1\nterminal D1 1 2",
+        )
+        .unwrap();
+        let pcfg = Pcfg::new(&model);
+        assert_eq!(pcfg.keyspace(), Ok(6));
+        // 3/4 * 2/3 * 3/4, 3/4 * 1/3 * 3/4, 1/4 * 3/4, 3/4 * 2/3 * 1/4, ...
+        assert_eq!(all(&pcfg), ["a1", "b1", "1", "a2", "b2", "2"]);
+
+        assert_eq!(pcfg.nth(5).as_deref(), Some("2"));
+        assert_eq!(pcfg.nth(6), None);
+        assert_eq!(pcfg.index_of("b2"), Some(4));
+        // no structure L1D2, no terminal c, no structure S1
+        assert_eq!(pcfg.index_of("a12"), None);
+        assert_eq!(pcfg.index_of("c1"), None);
+        assert_eq!(pcfg.index_of("!"), None);
+    }
+
+    #[test]
+    fn test_parts_cover_every_candidate_once() {
+        let model = PcfgModel::parse(
+            "structure L1D1L1 2\nstructure D1 1\n\
+             terminal L1 2 a\nterminal L1 1 b\nterminal L1 1 c\n\
+             terminal D1 3 1\nterminal D1 1 2",
+        )
+        .unwrap();
+        let pcfg = Pcfg::new(&model);
+        let expected = all(&pcfg);
+        assert_eq!(expected.len() as u128, pcfg.keyspace().unwrap());
+        assert_parts_cover_every_candidate_once(&expected, |offset, step| pcfg.part(offset, step));
+    }
+}
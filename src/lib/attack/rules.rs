@@ -0,0 +1,450 @@
+use std::{fs, io, path::Path};
+
+use thiserror::Error;
+
+/// Errors while parsing rules.
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("unknown rule function {0:?}")]
+    UnknownFunction(char),
+    #[error("rule function {0:?} is missing an argument")]
+    MissingArgument(char),
+    #[error("invalid position {0:?}, expected 0-9 or A-Z")]
+    InvalidPosition(char),
+    #[error("line {line}: {source}")]
+    Line {
+        line: usize,
+        #[source]
+        source: Box<RuleError>,
+    },
+    #[error("reading the rule file failed: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A single function of a rule. Positions are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Noop,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    Toggle(usize),
+    Reverse,
+    Duplicate,
+    DuplicateTimes(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(char),
+    Prepend(char),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Extract(usize, usize),
+    Truncate(usize),
+    Substitute(char, char),
+    Purge(char),
+    Insert(usize, char),
+    Overwrite(usize, char),
+    DuplicateFirst(usize),
+    DuplicateLast(usize),
+    DuplicateEach,
+    RejectLonger(usize),
+    RejectShorter(usize),
+    RejectUnlessLength(usize),
+    RejectContains(char),
+    RejectUnlessContains(char),
+    RejectUnlessStarts(char),
+    RejectUnlessEnds(char),
+}
+
+/// A word mangling rule in hashcat syntax, which shares the common functions with John.
+/// Where the two differ, e.g. for `p`, `<` & `>`, hashcat's meaning is used.
+///
+/// - case: `l` `u` `c` `C` `t` `TN`
+/// - order & repetition: `r` `d` `pN` `f` `{` `}` `q` `zN` `ZN`
+/// - append & prepend: `$X` `^X`
+/// - deletion & truncation: `[` `]` `DN` `xNM` `'N` `@X`
+/// - substitution & insertion: `sXY` `iNX` `oNX`
+/// - rejection: `<N` `>N` `_N` `!X` `/X` `(X` `)X`
+///
+/// Positions `N` & `M` are `0`-`9` & `A`-`Z` for 10 to 35. Spaces between functions are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    text: String,
+    ops: Vec<Op>,
+}
+
+impl Rule {
+    pub fn new(rule: &str) -> Result<Self, RuleError> {
+        let mut ops = vec![];
+        let mut chars = rule.chars();
+        while let Some(function) = chars.next() {
+            let mut char = || chars.next().ok_or(RuleError::MissingArgument(function));
+            let op = match function {
+                ' ' | '\t' => continue,
+                ':' => Op::Noop,
+                'l' => Op::Lower,
+                'u' => Op::Upper,
+                'c' => Op::Capitalize,
+                'C' => Op::InvertCapitalize,
+                't' => Op::ToggleAll,
+                'T' => Op::Toggle(position(char()?)?),
+                'r' => Op::Reverse,
+                'd' => Op::Duplicate,
+                'p' => Op::DuplicateTimes(position(char()?)?),
+                'f' => Op::Reflect,
+                '{' => Op::RotateLeft,
+                '}' => Op::RotateRight,
+                '$' => Op::Append(char()?),
+                '^' => Op::Prepend(char()?),
+                '[' => Op::DeleteFirst,
+                ']' => Op::DeleteLast,
+                'D' => Op::DeleteAt(position(char()?)?),
+                'x' => {
+                    let start = position(char()?)?;
+                    Op::Extract(start, position(char()?)?)
+                }
+                '\'' => Op::Truncate(position(char()?)?),
+                's' => {
+                    let from = char()?;
+                    Op::Substitute(from, char()?)
+                }
+                '@' => Op::Purge(char()?),
+                'i' => {
+                    let at = position(char()?)?;
+                    Op::Insert(at, char()?)
+                }
+                'o' => {
+                    let at = position(char()?)?;
+                    Op::Overwrite(at, char()?)
+                }
+                'z' => Op::DuplicateFirst(position(char()?)?),
+                'Z' => Op::DuplicateLast(position(char()?)?),
+                'q' => Op::DuplicateEach,
+                '<' => Op::RejectLonger(position(char()?)?),
+                '>' => Op::RejectShorter(position(char()?)?),
+                '_' => Op::RejectUnlessLength(position(char()?)?),
+                '!' => Op::RejectContains(char()?),
+                '/' => Op::RejectUnlessContains(char()?),
+                '(' => Op::RejectUnlessStarts(char()?),
+                ')' => Op::RejectUnlessEnds(char()?),
+                function => return Err(RuleError::UnknownFunction(function)),
+            };
+            ops.push(op);
+        }
+        Ok(Self {
+            text: rule.into(),
+            ops,
+        })
+    }
+
+    /// The rule as written.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Applies the rule to `word`, leaving the result in `word`.
+    /// Returns false if the word got rejected. Out of range positions leave the word unchanged.
+    pub fn apply(&self, word: &mut Vec<char>) -> bool {
+        for op in &self.ops {
+            let len = word.len();
+            match *op {
+                Op::Noop => {}
+                Op::Lower => word.iter_mut().for_each(|c| *c = lower(*c)),
+                Op::Upper => word.iter_mut().for_each(|c| *c = upper(*c)),
+                Op::Capitalize => {
+                    word.iter_mut().for_each(|c| *c = lower(*c));
+                    if let Some(first) = word.first_mut() {
+                        *first = upper(*first);
+                    }
+                }
+                Op::InvertCapitalize => {
+                    word.iter_mut().for_each(|c| *c = upper(*c));
+                    if let Some(first) = word.first_mut() {
+                        *first = lower(*first);
+                    }
+                }
+                Op::ToggleAll => word.iter_mut().for_each(|c| *c = toggle(*c)),
+                Op::Toggle(n) => {
+                    if let Some(c) = word.get_mut(n) {
+                        *c = toggle(*c);
+                    }
+                }
+                Op::Reverse => word.reverse(),
+                Op::Duplicate => word.extend_from_within(..),
+                Op::DuplicateTimes(n) => {
+                    for _ in 0..n {
+                        word.extend_from_within(..len);
+                    }
+                }
+                Op::Reflect => {
+                    word.extend_from_within(..);
+                    word[len..].reverse();
+                }
+                Op::RotateLeft if len > 0 => word.rotate_left(1),
+                Op::RotateRight if len > 0 => word.rotate_right(1),
+                Op::RotateLeft | Op::RotateRight => {}
+                Op::Append(c) => word.push(c),
+                Op::Prepend(c) => word.insert(0, c),
+                Op::DeleteFirst if len > 0 => {
+                    word.remove(0);
+                }
+                Op::DeleteLast => {
+                    word.pop();
+                }
+                Op::DeleteAt(n) if n < len => {
+                    word.remove(n);
+                }
+                Op::DeleteFirst | Op::DeleteAt(_) => {}
+                Op::Extract(start, count) if start < len => {
+                    word.truncate((start + count).min(len));
+                    word.drain(..start);
+                }
+                Op::Extract(..) => {}
+                Op::Truncate(n) => word.truncate(n),
+                Op::Substitute(from, to) => word
+                    .iter_mut()
+                    .filter(|c| **c == from)
+                    .for_each(|c| *c = to),
+                Op::Purge(c) => word.retain(|w| *w != c),
+                Op::Insert(n, c) if n <= len => word.insert(n, c),
+                Op::Overwrite(n, c) if n < len => word[n] = c,
+                Op::Insert(..) | Op::Overwrite(..) => {}
+                Op::DuplicateFirst(n) if len > 0 => {
+                    let first = word[0];
+                    word.splice(0..0, std::iter::repeat_n(first, n));
+                }
+                Op::DuplicateLast(n) if len > 0 => {
+                    let last = word[len - 1];
+                    word.extend(std::iter::repeat_n(last, n));
+                }
+                Op::DuplicateFirst(_) | Op::DuplicateLast(_) => {}
+                Op::DuplicateEach => {
+                    word.resize(len * 2, ' ');
+                    for i in (0..len).rev() {
+                        word[2 * i] = word[i];
+                        word[2 * i + 1] = word[i];
+                    }
+                }
+                Op::RejectLonger(n) if len > n => return false,
+                Op::RejectShorter(n) if len < n => return false,
+                Op::RejectUnlessLength(n) if len != n => return false,
+                Op::RejectContains(c) if word.contains(&c) => return false,
+                Op::RejectUnlessContains(c) if !word.contains(&c) => return false,
+                Op::RejectUnlessStarts(c) if word.first() != Some(&c) => return false,
+                Op::RejectUnlessEnds(c) if word.last() != Some(&c) => return false,
+                Op::RejectLonger(_)
+                | Op::RejectShorter(_)
+                | Op::RejectUnlessLength(_)
+                | Op::RejectContains(_)
+                | Op::RejectUnlessContains(_)
+                | Op::RejectUnlessStarts(_)
+                | Op::RejectUnlessEnds(_) => {}
+            }
+        }
+        true
+    }
+}
+
+/// The rules of a rule file, applied in order to every word.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// One rule per line; empty lines & lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self, RuleError> {
+        let rules = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim_end_matches('\r')))
+            .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                Rule::new(line).map_err(|e| RuleError::Line {
+                    line: i + 1,
+                    source: Box::new(e),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RuleError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// How a rule did during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleStats {
+    rule: String,
+    candidates: usize,
+    rejected: usize,
+    hits: usize,
+}
+
+impl RuleStats {
+    pub(crate) fn new(rule: &Rule) -> Self {
+        Self {
+            rule: rule.text().into(),
+            candidates: 0,
+            rejected: 0,
+            hits: 0,
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: &RuleStats) {
+        self.candidates += other.candidates;
+        self.rejected += other.rejected;
+        self.hits += other.hits;
+    }
+
+    pub(crate) fn count(&mut self, accepted: bool) {
+        if accepted {
+            self.candidates += 1;
+        } else {
+            self.rejected += 1;
+        }
+    }
+
+    pub(crate) fn hit(&mut self) {
+        self.hits += 1;
+    }
+
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// Candidates the rule produced.
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+
+    /// Words the rule rejected.
+    pub fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Candidates of the rule that turned out to be correct.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+/// Position argument, `0`-`9` & `A`-`Z`.
+fn position(char: char) -> Result<usize, RuleError> {
+    match char {
+        '0'..='9' => Ok(char as usize - '0' as usize),
+        'A'..='Z' => Ok(char as usize - 'A' as usize + 10),
+        char => Err(RuleError::InvalidPosition(char)),
+    }
+}
+
+fn lower(char: char) -> char {
+    char.to_lowercase().next().unwrap_or(char)
+}
+
+fn upper(char: char) -> char {
+    char.to_uppercase().next().unwrap_or(char)
+}
+
+fn toggle(char: char) -> char {
+    if char.is_lowercase() {
+        upper(char)
+    } else {
+        lower(char)
+    }
+}
+
+#[cfg(test)]
+mod tests_rules {
+    use super::*;
+
+    fn apply(rule: &str, word: &str) -> Option<String> {
+        let mut word = word.chars().collect();
+        Rule::new(rule)
+            .unwrap()
+            .apply(&mut word)
+            .then(|| word.into_iter().collect())
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(apply(":", "pass").unwrap(), "pass");
+        assert_eq!(apply("c $1 $!", "pASSword").unwrap(), "Password1!");
+        assert_eq!(apply("u", "pass").unwrap(), "PASS");
+        assert_eq!(apply("C", "pass").unwrap(), "pASS");
+        assert_eq!(apply("t T0", "PaSs").unwrap(), "PAsS");
+        assert_eq!(apply("r", "abc").unwrap(), "cba");
+        assert_eq!(apply("d", "ab").unwrap(), "abab");
+        assert_eq!(apply("p2", "ab").unwrap(), "ababab");
+        assert_eq!(apply("f", "ab").unwrap(), "abba");
+        assert_eq!(apply("{", "abc").unwrap(), "bca");
+        assert_eq!(apply("}", "abc").unwrap(), "cab");
+        assert_eq!(apply("^1 ^2", "ab").unwrap(), "21ab");
+        assert_eq!(apply("[ ]", "abcd").unwrap(), "bc");
+        assert_eq!(apply("D1", "abcd").unwrap(), "acd");
+        assert_eq!(apply("x12", "abcd").unwrap(), "bc");
+        assert_eq!(apply("'2", "abcd").unwrap(), "ab");
+        assert_eq!(apply("sa@ so0", "foobar").unwrap(), "f00b@r");
+        assert_eq!(apply("@o", "foobar").unwrap(), "fbar");
+        assert_eq!(apply("i1- o0X", "ab").unwrap(), "X-b");
+        assert_eq!(apply("z2 Z1", "ab").unwrap(), "aaabb");
+        assert_eq!(apply("q", "ab").unwrap(), "aabb");
+        // out of range positions are ignored
+        assert_eq!(apply("DZ T9 i9x", "ab").unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_rejections() {
+        assert_eq!(apply("<3", "abcd"), None);
+        assert_eq!(apply("<4", "abcd").unwrap(), "abcd");
+        assert_eq!(apply(">5", "abcd"), None);
+        assert_eq!(apply("_4", "abcd").unwrap(), "abcd");
+        assert_eq!(apply("!b", "abcd"), None);
+        assert_eq!(apply("/z", "abcd"), None);
+        assert_eq!(apply("(a )d", "abcd").unwrap(), "abcd");
+        // rejections look at the word as mangled so far
+        assert_eq!(apply("$1 )1", "abcd").unwrap(), "abcd1");
+    }
+
+    #[test]
+    fn test_parse() {
+        let rules = Rules::parse("# comment\n:\r\n\nc $1\n").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules.rules()[1].text(), "c $1");
+
+        assert!(matches!(
+            Rule::new("$"),
+            Err(RuleError::MissingArgument('$'))
+        ));
+        assert!(matches!(
+            Rule::new("T!"),
+            Err(RuleError::InvalidPosition('!'))
+        ));
+        assert!(matches!(
+            Rule::new("K"),
+            Err(RuleError::UnknownFunction('K'))
+        ));
+        assert!(matches!(
+            Rules::parse(":\nc\nY"),
+            Err(RuleError::Line { line: 3, .. })
+        ));
+    }
+}
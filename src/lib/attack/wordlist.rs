@@ -0,0 +1,414 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+
+use crate::attack::{Candidates, Progress, RuleStats, Rules};
+
+/// A file with one candidate per line. Lines may end with LF, CRLF or CR.
+/// Empty lines & comment lines are skipped, everything else is used as is,
+/// or mangled by every rule if there are rules.
+///
+/// Lines have to be UTF-8. Other lines, e.g. from a Latin-1 list, are skipped with a warning,
+/// as replacing their invalid bytes would try candidates that aren't in the list.
+#[derive(Debug, Clone)]
+pub struct Wordlist {
+    path: PathBuf,
+    comment_prefix: Option<String>,
+    rules: Rules,
+}
+
+impl Wordlist {
+    /// Lines starting with `#` are comments by default.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            comment_prefix: Some("#".into()),
+            rules: Rules::default(),
+        }
+    }
+
+    /// Applies every rule to every word, instead of using the words as they are.
+    /// Include the `:` rule to also try the unchanged words.
+    pub fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn with_comment_prefix(mut self, prefix: &str) -> Self {
+        self.comment_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Uses every non-empty line, e.g. for lists with passwords starting with `#`.
+    pub fn without_comments(mut self) -> Self {
+        self.comment_prefix = None;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn comment_prefix(&self) -> Option<&str> {
+        self.comment_prefix.as_deref()
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Size of the file in bytes.
+    pub fn len(&self) -> io::Result<u64> {
+        Ok(self.path.metadata()?.len())
+    }
+
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Reads every candidate of the wordlist, i.e. every word mangled by every rule, into memory.
+    pub(crate) fn words(&self) -> io::Result<Vec<String>> {
+        let mut part = self.open_part(0, 1)?;
+        let mut words = vec![];
+        let mut buf = String::new();
+        while part.next(&mut buf) {
+            words.push(buf.clone());
+        }
+        part.take_error()?;
+        Ok(words)
+    }
+
+    /// The word at index `n`, with the rules applied, None past the last one.
+    /// Reads the file from the start.
+    pub fn nth(&self, n: u128) -> io::Result<Option<String>> {
+        let mut part = self.open_part(0, 1)?;
+        let mut buf = String::new();
+        for _ in 0..=n {
+            if !part.next(&mut buf) {
+                part.take_error()?;
+                return Ok(None);
+            }
+        }
+        Ok(Some(buf))
+    }
+
+    /// The first index of `candidate`, None if the wordlist doesn't produce it.
+    /// Reads the file up to it.
+    pub fn index_of(&self, candidate: &str) -> io::Result<Option<u128>> {
+        let mut part = self.open_part(0, 1)?;
+        let mut buf = String::new();
+        let mut index = 0;
+        while part.next(&mut buf) {
+            if buf == candidate {
+                return Ok(Some(index));
+            }
+            index += 1;
+        }
+        part.take_error()?;
+        Ok(None)
+    }
+
+    /// Opens the `part`th of `parts` equally sized byte ranges of the file.
+    /// A line belongs to the range its first byte is in, so every line is read exactly once.
+    pub(crate) fn open_part(&self, part: usize, parts: usize) -> io::Result<WordlistPart> {
+        let len = self.len()?;
+        let start = len * part as u64 / parts as u64;
+        let end = len * (part as u64 + 1) / parts as u64;
+        self.open_range(start, end)
+    }
+
+    /// Opens the lines that start in the byte range, see `open_part`.
+    pub(crate) fn open_range(&self, start: u64, end: u64) -> io::Result<WordlistPart> {
+        let mut file = File::open(&self.path)?;
+        let mut pos = start;
+        if start > 0 {
+            // a line only starts here, if the previous byte ended a line
+            file.seek(SeekFrom::Start(start - 1))?;
+            let mut previous = [0];
+            file.read_exact(&mut previous)?;
+            let mut reader = BufReader::new(file);
+            if !is_line_end(previous[0]) {
+                pos += read_line(&mut reader, &mut Vec::new())? as u64;
+            }
+            file = reader.into_inner();
+            file.seek(SeekFrom::Start(pos))?;
+        }
+
+        Ok(WordlistPart {
+            path: self.path.clone(),
+            reader: BufReader::new(file),
+            comment_prefix: self.comment_prefix.clone(),
+            start,
+            end,
+            pos,
+            lines: 0,
+            skipped: 0,
+            error: None,
+            line: Vec::new(),
+            stats: self.rules.rules().iter().map(RuleStats::new).collect(),
+            rules: self.rules.clone(),
+            rule: self.rules.len(),
+            last_rule: None,
+            word: String::new(),
+            chars: Vec::new(),
+            mangled: Vec::new(),
+        })
+    }
+}
+
+/// The lines of one byte range of a wordlist.
+pub(crate) struct WordlistPart {
+    path: PathBuf,
+    reader: BufReader<File>,
+    comment_prefix: Option<String>,
+    start: u64,
+    end: u64,
+    // offset of the next line in the file
+    pos: u64,
+    lines: u64,
+    // lines that aren't UTF-8
+    skipped: u64,
+    // why the part ended early
+    error: Option<io::Error>,
+    // reused for every line, so that the loop doesn't allocate
+    line: Vec<u8>,
+    rules: Rules,
+    stats: Vec<RuleStats>,
+    // the next rule to apply to the current word
+    rule: usize,
+    // the rule of the last candidate
+    last_rule: Option<usize>,
+    // the current word, as string & as chars for the rules
+    word: String,
+    chars: Vec<char>,
+    mangled: Vec<char>,
+}
+
+impl WordlistPart {
+    /// The number of words read so far.
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Where the line after the current word starts.
+    pub fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Leaves the lines starting at or after `end` to another part, e.g. once they're stolen.
+    pub fn limit(&mut self, end: u64) {
+        self.end = self.end.min(end);
+    }
+
+    /// The read error that ended the part early, if any.
+    pub fn take_error(&mut self) -> io::Result<()> {
+        self.error.take().map_or(Ok(()), Err)
+    }
+
+    /// Reads the next word of the part into `buf`.
+    fn next_word(&mut self, buf: &mut String) -> bool {
+        // lines starting at the end belong to the next part
+        while self.pos < self.end {
+            let read = match read_line(&mut self.reader, &mut self.line) {
+                Ok(0) => return false,
+                Ok(read) => read,
+                Err(e) => {
+                    self.error = Some(e);
+                    return false;
+                }
+            };
+            let line_start = self.pos;
+            self.pos += read as u64;
+
+            if self.line.is_empty() {
+                continue;
+            }
+            let Ok(line) = std::str::from_utf8(&self.line) else {
+                // once per part, a list in another encoding would flood the log
+                if self.skipped == 0 {
+                    warn!(
+                        "Skipping the lines of wordlist {} that aren't UTF-8, the first at byte {}",
+                        self.path.display(),
+                        line_start
+                    );
+                }
+                self.skipped += 1;
+                continue;
+            };
+            if matches!(&self.comment_prefix, Some(prefix) if line.starts_with(prefix.as_str())) {
+                continue;
+            }
+            self.lines += 1;
+            buf.clear();
+            buf.push_str(line);
+            return true;
+        }
+        false
+    }
+}
+
+impl Candidates for WordlistPart {
+    fn next(&mut self, buf: &mut String) -> bool {
+        if self.rules.is_empty() {
+            return self.next_word(buf);
+        }
+        loop {
+            while self.rule < self.rules.len() {
+                let i = self.rule;
+                self.rule += 1;
+                self.mangled.clone_from(&self.chars);
+                let accepted = self.rules.rules()[i].apply(&mut self.mangled);
+                self.stats[i].count(accepted);
+                if accepted {
+                    self.last_rule = Some(i);
+                    buf.clear();
+                    buf.extend(&self.mangled);
+                    return true;
+                }
+            }
+
+            let mut word = std::mem::take(&mut self.word);
+            let more = self.next_word(&mut word);
+            self.chars.clear();
+            self.chars.extend(word.chars());
+            self.word = word;
+            if !more {
+                return false;
+            }
+            self.rule = 0;
+        }
+    }
+
+    fn hit(&mut self) {
+        if let Some(rule) = self.last_rule {
+            self.stats[rule].hit();
+        }
+    }
+
+    fn rule_stats(&self) -> Vec<RuleStats> {
+        self.stats.clone()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress::Bytes {
+            done: self.pos.min(self.end).saturating_sub(self.start),
+            total: self.end - self.start,
+            lines: self.lines,
+        }
+    }
+}
+
+fn is_line_end(byte: u8) -> bool {
+    byte == b'\n' || byte == b'\r'
+}
+
+/// Reads up to & including the next LF or CR into `line`, without the line end.
+/// Returns the number of bytes consumed, 0 at the end of the file.
+/// A CRLF is read as a line & an empty line, the latter gets skipped like any empty line.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<usize> {
+    line.clear();
+    let mut read = 0;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(read);
+        }
+        match available.iter().position(|byte| is_line_end(*byte)) {
+            Some(i) => {
+                line.extend_from_slice(&available[..i]);
+                reader.consume(i + 1);
+                return Ok(read + i + 1);
+            }
+            None => {
+                let n = available.len();
+                line.extend_from_slice(available);
+                reader.consume(n);
+                read += n;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_wordlist {
+    use super::*;
+    use crate::attack::tests_attack::TempFile;
+
+    fn read_all(wordlist: &Wordlist, parts: usize) -> Vec<String> {
+        let mut words = vec![];
+        let mut buf = String::new();
+        for part in 0..parts {
+            let mut part = wordlist.open_part(part, parts).unwrap();
+            while part.next(&mut buf) {
+                words.push(buf.clone());
+            }
+            assert_eq!(part.progress().percent(), 100.0);
+        }
+        words
+    }
+
+    #[test]
+    fn test_line_endings_and_comments() {
+        let file = TempFile::new("endings", "# comment\nfoo\r\nbar\rbaz\n\n  \n#x\nqux");
+        let wordlist = file.wordlist();
+        let expected = ["foo", "bar", "baz", "  ", "qux"];
+        assert_eq!(read_all(&wordlist, 1), expected);
+        assert_eq!(
+            read_all(&wordlist.clone().without_comments(), 1),
+            ["# comment", "foo", "bar", "baz", "  ", "#x", "qux"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_and_read_errors() {
+        // Latin-1 for café
+        let file = TempFile::new("latin1", b"foo\ncaf\xe9\nbar\n");
+        assert_eq!(file.wordlist().words().unwrap(), ["foo", "bar"]);
+
+        // reading a directory fails after opening it
+        let dir = std::env::temp_dir();
+        let mut part = Wordlist::new(&dir).open_part(0, 1).unwrap();
+        let mut buf = String::new();
+        assert!(!part.next(&mut buf));
+        if dir.metadata().unwrap().len() > 0 {
+            assert!(part.take_error().is_err());
+            assert!(Wordlist::new(&dir).words().is_err());
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = Rules::parse(":\nc $1\n_2 r").unwrap();
+        let file = TempFile::new("rules", "abc\nxy\n");
+        let wordlist = file.wordlist().with_rules(rules);
+        assert_eq!(read_all(&wordlist, 1), ["abc", "Abc1", "xy", "Xy1", "yx"]);
+
+        let mut part = wordlist.open_part(0, 1).unwrap();
+        let mut buf = String::new();
+        while part.next(&mut buf) && buf != "Xy1" {}
+        part.hit();
+        let stats = part.rule_stats();
+        assert_eq!(stats[1].rule(), "c $1");
+        assert_eq!((stats[1].candidates(), stats[1].hits()), (2, 1));
+        assert_eq!((stats[2].candidates(), stats[2].rejected()), (0, 1));
+    }
+
+    #[test]
+    fn test_parts_cover_every_line_once() {
+        let content = (0..100)
+            .map(|i| format!("word{}", i))
+            .collect::<Vec<_>>()
+            .join("\r\n");
+        let file = TempFile::new("parts", &content);
+        let wordlist = file.wordlist();
+        let expected = read_all(&wordlist, 1);
+        assert_eq!(expected.len(), 100);
+        for parts in [2, 3, 7, 64, 1000] {
+            assert_eq!(read_all(&wordlist, parts), expected, "{} parts", parts);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use std::{
+    ffi::OsStr,
+    io::{self, ErrorKind, Read, Write},
+    process::{Child, Command, Output, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::oracle::{Oracle, Response, SuccessCriterion, Verdict};
+use crate::parameters::Spawn;
+
+/// Written after every candidate, so that line based targets pick it up.
+const TERMINATOR: &[u8] = b"\n";
+
+/// How often spawning is retried when the system runs out of processes.
+const SPAWN_RETRIES: u32 = 10;
+
+/// Oracle that starts a fresh process for every candidate.
+/// The candidate is written to stdin, which is closed afterwards; stdout & the exit status
+/// are collected once the process exited, stderr only if the criterion looks at it.
+/// A process that runs longer than the spawn's timeout is killed, together with the
+/// processes it started, & the candidate reported as an oracle error.
+pub struct AttemptOracle<I, S> {
+    // built once & reused for every spawn, which saves the allocations per attempt
+    command: Command,
+    criterion: SuccessCriterion,
+    timeout: Duration,
+    watchdog: Sender<Watch>,
+    _spawn: std::marker::PhantomData<Spawn<I, S>>,
+}
+
+/// How an attempt ended.
+enum Attempt {
+    Exited(Output),
+    TimedOut,
+}
+
+/// A child the watchdog kills at the deadline, unless the attempt finished before.
+struct Watch {
+    child: Child,
+    deadline: Instant,
+    // dropped once the child exited & its stdout is read
+    finished: Receiver<()>,
+    // the child back & whether it got killed, before anyone reaps it
+    released: Sender<(Child, bool)>,
+}
+
+/// Starts the thread that watches every attempt of an oracle, one after the other.
+/// It stops once the oracle is dropped.
+fn watchdog() -> Sender<Watch> {
+    let (sender, receiver) = mpsc::channel::<Watch>();
+    thread::spawn(move || {
+        for mut watch in receiver {
+            let remaining = watch.deadline.saturating_duration_since(Instant::now());
+            let killed = watch.finished.recv_timeout(remaining) == Err(RecvTimeoutError::Timeout);
+            if killed {
+                if let Err(e) = kill(&mut watch.child) {
+                    warn!("Couldn't kill child {}: {}", watch.child.id(), e);
+                }
+            }
+            let _ = watch.released.send((watch.child, killed));
+        }
+    });
+    sender
+}
+
+impl<I, S> AttemptOracle<I, S> {
+    /// Spawns the target. Running into the process limit (EAGAIN) is retried with a
+    /// growing delay, so that a burst of short lived children doesn't abort the run.
+    fn spawn(&mut self) -> io::Result<Child> {
+        let mut delay = Duration::from_millis(1);
+        for _ in 0..SPAWN_RETRIES {
+            match self.command.spawn() {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    warn!("Process limit reached, retrying in {:?}.", delay);
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+        Err(io::Error::new(
+            ErrorKind::WouldBlock,
+            "process limit reached",
+        ))
+    }
+
+    /// Runs the target once with the candidate as input. Stdout is read on the calling
+    /// thread, while the watchdog holds the child to kill it at the deadline.
+    fn run(&mut self, candidate: &str) -> io::Result<Attempt> {
+        let deadline = Instant::now() + self.timeout;
+        let mut child = self.spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        // read concurrently, so that a full pipe can't block the target
+        let stderr = child.stderr.take().map(read_all);
+        let pid = child.id();
+
+        let (finished, watched) = mpsc::channel();
+        let (released, release) = mpsc::channel();
+        let watch = Watch {
+            child,
+            deadline,
+            finished: watched,
+            released,
+        };
+        if let Err(mpsc::SendError(mut watch)) = self.watchdog.send(watch) {
+            let _ = kill(&mut watch.child);
+            watch.child.wait()?;
+            return Err(io::Error::other("the watchdog is gone"));
+        }
+
+        // Targets may exit without reading their input, the resulting broken pipe is fine.
+        let _ = stdin
+            .write_all(candidate.as_bytes())
+            .and_then(|_| stdin.write_all(TERMINATOR));
+        // closing stdin signals EOF to the target
+        drop(stdin);
+        let mut output = vec![];
+        let read = stdout.read_to_end(&mut output);
+        // the target may close its stdout & keep running
+        let exited = wait_exited(pid);
+
+        drop(finished);
+        let (mut child, killed) = release
+            .recv()
+            .map_err(|_| io::Error::other("the watchdog is gone"))?;
+        let status = child.wait()?;
+        if killed {
+            return Ok(Attempt::TimedOut);
+        }
+        read?;
+        exited?;
+        let stderr = match stderr {
+            // processes the target started may still hold the pipe open
+            Some(stderr) => {
+                match stderr.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(stderr) => stderr?,
+                    Err(_) => return Ok(Attempt::TimedOut),
+                }
+            }
+            None => vec![],
+        };
+        Ok(Attempt::Exited(Output {
+            status,
+            stdout: output,
+            stderr,
+        }))
+    }
+}
+
+/// Reads the pipe to its end on a thread of its own.
+fn read_all(mut pipe: impl Read + Send + 'static) -> Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = vec![];
+        let _ = sender.send(pipe.read_to_end(&mut buf).map(|_| buf));
+    });
+    receiver
+}
+
+/// Blocks until the child exited, without reaping it, so that the watchdog can't kill
+/// another process that got its pid.
+#[cfg(unix)]
+fn wait_exited(pid: u32) -> io::Result<()> {
+    loop {
+        // SAFETY: the info is plain data & only written by the call
+        let result = unsafe {
+            let mut info: libc::siginfo_t = std::mem::zeroed();
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if result == 0 {
+            return Ok(());
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+}
+
+/// Elsewhere a target that closes its stdout but keeps running is waited for without a deadline.
+#[cfg(not(unix))]
+fn wait_exited(_pid: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Kills the child & the processes it started, they share its process group.
+#[cfg(unix)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    // SAFETY: no pointers; the child isn't reaped yet, so the group is still its own
+    if unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Elsewhere only the child itself.
+#[cfg(not(unix))]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+impl<I, S> Oracle for AttemptOracle<I, S>
+where
+    I: IntoIterator<Item = S> + Clone + Send + Sync + 'static,
+    S: AsRef<OsStr> + Clone + Send + Sync + 'static,
+{
+    type Config = Spawn<I, S>;
+
+    fn create(config: &Self::Config, _tid: usize) -> Self {
+        let mut command = Command::new(config.script());
+        let stderr = if config.criterion().reads_stderr() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
+        command
+            .args(config.args().clone())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr);
+        // a group of its own, so that a timeout kills whatever the target started, too
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+        Self {
+            command,
+            criterion: config.criterion().clone(),
+            timeout: config.timeout(),
+            watchdog: watchdog(),
+            _spawn: std::marker::PhantomData,
+        }
+    }
+
+    fn check(&mut self, candidate: &str) -> Verdict {
+        match self.run(candidate) {
+            Ok(Attempt::Exited(output)) => self.criterion.judge(&Response::from(output)),
+            Ok(Attempt::TimedOut) => {
+                warn!(
+                    "Child didn't finish \"{}\" within {:?}, killed it.",
+                    candidate, self.timeout
+                );
+                Verdict::Error(format!("no answer within {:?}", self.timeout))
+            }
+            Err(e) => Verdict::Error(format!("running the child failed: {}", e)),
+        }
+    }
+
+    fn close(self) {}
+}
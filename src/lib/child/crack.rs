@@ -0,0 +1,408 @@
+use std::ffi::OsStr;
+
+use crate::child::{attempt::AttemptOracle, oracle::ChildOracle};
+use crate::engine::{self, CrackError, CrackHandle, CrackResult};
+use crate::parameters::{CrackParam, SpawnMode};
+
+/// Cracks the password of a native target. Depending on the spawn mode every worker
+/// either keeps one child process alive & feeds it a candidate per line, or starts
+/// a fresh process for every candidate. Fails if the attack's keyspace overflows,
+/// if its wordlist can't be read, for `Workers::Count(0)`, or if the criterion looks
+/// at stderr or the exit code in `SpawnMode::Persistent`.
+pub fn crack<I, S>(param: CrackParam<I, S>) -> Result<CrackResult, CrackError>
+where
+    I: IntoIterator<Item = S> + Clone + Send + Sync + 'static,
+    S: AsRef<OsStr> + Clone + Send + Sync + 'static,
+{
+    Ok(start(param)?.wait())
+}
+
+/// Like `crack`, but returns right away, to cancel, pause or watch the workers.
+pub fn start<I, S>(param: CrackParam<I, S>) -> Result<CrackHandle, CrackError>
+where
+    I: IntoIterator<Item = S> + Clone + Send + Sync + 'static,
+    S: AsRef<OsStr> + Clone + Send + Sync + 'static,
+{
+    let search = param.search().clone();
+    let criterion = param.spawn().criterion();
+    match param.spawn().mode() {
+        // the child keeps running & its stderr isn't read
+        SpawnMode::Persistent if criterion.reads_stderr() => Err(CrackError::Criterion("stderr")),
+        SpawnMode::Persistent if criterion.reads_exit_code() => {
+            Err(CrackError::Criterion("exit code"))
+        }
+        SpawnMode::Persistent => engine::start::<ChildOracle<I, S>>(search, param.spawn().clone()),
+        SpawnMode::PerAttempt => {
+            engine::start::<AttemptOracle<I, S>>(search, param.spawn().clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_crack {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::attack::tests_attack::TempFile;
+    use crate::attack::Progress;
+    use crate::attack::{
+        Attack, CaseStyle, Combinator, Hybrid, Markov, MarkovModel, Mask, Passphrase, Pcfg,
+        PcfgModel, Rules, Wordlist,
+    };
+    use crate::engine::{WorkerOptions, Workers};
+    use crate::oracle::{Matcher, SuccessCriterion};
+    use crate::parameters::{Spawn, SpawnMode};
+    use crate::symbols::KeyspaceOverflow;
+
+    /// Reads candidates line by line & only accepts "ba".
+    const TARGET: &str =
+        "while read l; do if [ \"$l\" = ba ]; then echo correct; else echo wrong; fi; done";
+
+    #[test]
+    fn test_crack_persistent_child() {
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+    }
+
+    #[test]
+    fn test_crack_persistent_child_failure() {
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(CrackParam::new(spawn, Box::from(['a']), 0, 3)).unwrap();
+        assert!(res.is_failure());
+    }
+
+    #[test]
+    fn test_crack_persistent_child_dies() {
+        // exits on every line without answering, even after the restart
+        let spawn = Spawn::new("sh", vec!["-c", "read l; exit 1"], "correct");
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 0, 1)).unwrap();
+        assert!(res.is_failure());
+        assert_eq!(res.oracle_errors(), 3);
+    }
+
+    #[test]
+    fn test_crack_persistent_child_timeout() {
+        // stops answering at "a" without exiting
+        let target = "while read l; do [ \"$l\" = a ] && exec sleep 5; \
+            if [ \"$l\" = ba ]; then echo correct; else echo wrong; fi; done";
+        let spawn = Spawn::new("sh", vec!["-c", target], "correct")
+            .with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)
+                .with_worker_options(WorkerOptions::new().with_workers(Workers::Count(1))),
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // the restarted child answers the rest
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.oracle_errors(), 1);
+    }
+
+    #[test]
+    fn test_crack_workers() {
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let options = WorkerOptions::new()
+            .with_workers(Workers::Count(3))
+            .with_pinned_cpus(&[0])
+            .with_low_priority();
+        let param = CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)
+            .with_worker_options(options.clone());
+        let res = crack(param.clone()).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.thread_count(), 3);
+
+        let res = crack(
+            param
+                .clone()
+                .with_worker_options(options.clone().with_workers(Workers::Auto)),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert!(res.thread_count() >= 1);
+
+        let param = param.with_worker_options(options.with_workers(Workers::Count(0)));
+        assert!(matches!(crack(param), Err(CrackError::NoWorkers)));
+    }
+
+    #[test]
+    fn test_start_pause_resume_cancel() {
+        // accepts nothing, so only cancel stops it
+        let target = "while read l; do echo wrong; done";
+        let spawn = Spawn::new("sh", vec!["-c", target], "correct");
+        let handle = start(
+            CrackParam::new(spawn, Box::from(['a', 'b', 'c', 'd']), 0, 12)
+                .with_worker_options(WorkerOptions::new().with_workers(Workers::Count(2))),
+        )
+        .unwrap();
+        let done = |handle: &CrackHandle| match handle.progress() {
+            Progress::Candidates { done, .. } => done,
+            progress => panic!("{:?}", progress),
+        };
+        while done(&handle) == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        handle.pause();
+        assert!(handle.is_paused());
+        // the workers finish the candidate they are on
+        handle.wait_until_paused();
+        let paused = done(&handle);
+        assert!(!handle.is_finished());
+        assert_eq!(done(&handle), paused);
+
+        handle.resume();
+        while done(&handle) == paused {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(!handle.is_finished());
+
+        handle.cancel();
+        let res = handle.wait();
+        assert!(res.is_failure());
+        assert!(res.is_cancelled());
+        assert!(res.candidates_tried() > 0);
+    }
+
+    #[test]
+    fn test_persistent_rejects_stderr_and_exit_code_criteria() {
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        for (criterion, needs) in [
+            (SuccessCriterion::StderrEmpty, "stderr"),
+            (SuccessCriterion::StderrNotEmpty, "stderr"),
+            (SuccessCriterion::ExitCode(0), "exit code"),
+            (SuccessCriterion::ExitCodeNot(1), "exit code"),
+            (
+                SuccessCriterion::StdoutMatches(Matcher::literal("correct"))
+                    .and(SuccessCriterion::StderrEmpty.not()),
+                "stderr",
+            ),
+        ] {
+            let param = CrackParam::new(
+                spawn.clone().with_criterion(criterion),
+                Box::from(['a', 'b']),
+                0,
+                2,
+            );
+            assert!(
+                matches!(start(param), Err(CrackError::Criterion(lacking)) if lacking == needs),
+                "{}",
+                needs
+            );
+        }
+    }
+
+    #[test]
+    fn test_crack_per_attempt() {
+        // reads a single line & exits, like most toy targets do
+        let target = "read l; if [ \"$l\" = ab ]; then echo correct; else echo wrong; fi";
+        let spawn =
+            Spawn::new("sh", vec!["-c", target], "correct").with_mode(SpawnMode::PerAttempt);
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn test_crack_per_attempt_timeout() {
+        let spawn = Spawn::new("sh", vec!["-c", "read l; exec sleep 5"], "correct")
+            .with_mode(SpawnMode::PerAttempt)
+            .with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        let res = crack(CrackParam::new(spawn, Box::from(['a']), 1, 1)).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(res.is_failure());
+        assert_eq!(res.oracle_errors(), 1);
+
+        // the sleep the target started holds its stdout open & gets killed with it
+        let spawn = Spawn::new("sh", vec!["-c", "read l; sleep 5; echo wrong"], "correct")
+            .with_mode(SpawnMode::PerAttempt)
+            .with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        let res = crack(CrackParam::new(spawn, Box::from(['a']), 1, 1)).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(res.oracle_errors(), 1);
+    }
+
+    #[test]
+    fn test_crack_per_attempt_stderr() {
+        // only complains on stderr
+        let target = "read l; [ \"$l\" = ab ] || echo wrong >&2";
+        let spawn = Spawn::new("sh", vec!["-c", target], "")
+            .with_mode(SpawnMode::PerAttempt)
+            .with_criterion(SuccessCriterion::StderrEmpty);
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 1, 2)).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ab"));
+    }
+
+    #[test]
+    fn test_crack_per_attempt_spawn_error() {
+        let spawn =
+            Spawn::new("/nonexistent/target", vec![], "correct").with_mode(SpawnMode::PerAttempt);
+        let res = crack(CrackParam::new(spawn, Box::from(['a']), 1, 1)).unwrap();
+        assert!(res.is_failure());
+        assert_eq!(res.oracle_errors(), 1);
+    }
+
+    #[test]
+    fn test_crack_per_attempt_exit_code() {
+        // silent target, only the exit code tells whether the password was right
+        let target = "read l; [ \"$l\" = bb ]";
+        let spawn = Spawn::new("sh", vec!["-c", target], "")
+            .with_mode(SpawnMode::PerAttempt)
+            .with_criterion(SuccessCriterion::ExitCode(0));
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("bb"));
+    }
+
+    #[test]
+    fn test_crack_keyspace_overflow() {
+        let printable: Box<[char]> = (' '..='~').collect();
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        assert!(matches!(
+            crack(CrackParam::new(spawn, printable, 0, 20)),
+            Err(CrackError::Keyspace(KeyspaceOverflow))
+        ));
+    }
+
+    #[test]
+    fn test_crack_wordlist() {
+        let file = TempFile::new("crack", "# top passwords\nletmein\r\n\nba\nqwerty\n");
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                .with_attack(Attack::Wordlist(file.wordlist())),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.candidates_tried(), 2);
+        assert!(res.error().is_none());
+
+        let missing = file.path().with_extension("missing");
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        assert!(matches!(
+            crack(
+                CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                    .with_attack(Attack::Wordlist(Wordlist::new(missing)))
+            ),
+            Err(CrackError::Wordlist(_))
+        ));
+    }
+
+    #[test]
+    fn test_crack_wordlist_rules() {
+        let file = TempFile::new("rules", "ab\n");
+        let rules = Rules::parse(":\nr\nu").unwrap();
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                .with_attack(Attack::Wordlist(file.wordlist().with_rules(rules))),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        let hits = res
+            .rule_stats()
+            .iter()
+            .map(|s| s.hits())
+            .collect::<Vec<_>>();
+        assert_eq!(hits, [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_crack_mask() {
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let mask = Mask::with_classes("?1a", &[&['a', 'b', 'c']]).unwrap();
+        let res =
+            crack(CrackParam::new(spawn, Box::from(['a']), 0, 0).with_attack(Attack::Mask(mask)))
+                .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.combinations_total(), 3);
+    }
+
+    #[test]
+    fn test_crack_hybrid() {
+        let file = TempFile::new("hybrid", "x\nb\n");
+        let mask = Mask::with_classes("?1", &[&['a', 'b']]).unwrap();
+        let hybrid = Hybrid::wordlist_mask(&file.wordlist(), mask).unwrap();
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0).with_attack(Attack::Hybrid(hybrid)),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.combinations_total(), 4);
+    }
+
+    #[test]
+    fn test_crack_combinator() {
+        let first = TempFile::new("first", "x\nB\n");
+        let second = TempFile::new("second", "a\n");
+        let combinator = Combinator::new(&[first.wordlist(), second.wordlist()])
+            .unwrap()
+            .with_case_styles(0, &[CaseStyle::Upper, CaseStyle::Lower]);
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                .with_attack(Attack::Combinator(combinator)),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.combinations_total(), 4);
+    }
+
+    #[test]
+    fn test_crack_markov() {
+        // b starts words, a follows b
+        let model = MarkovModel::parse("^ 98 2\n98 97 1").unwrap();
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a', 'b']), 2, 2)
+                .with_attack(Attack::Markov(Markov::new(model))),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.candidates_tried(), 1);
+    }
+
+    #[test]
+    fn test_crack_pcfg() {
+        let file = TempFile::new("pcfg", "ba\nab12\nxy!\n");
+        let model = PcfgModel::train(&file.wordlist()).unwrap();
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                .with_attack(Attack::Pcfg(Pcfg::new(&model))),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.combinations_total(), 9);
+    }
+
+    #[test]
+    fn test_crack_passphrase() {
+        let tokens = vec!["x".into(), "B".into(), "a".into()];
+        let passphrase = Passphrase::new(tokens, 2, 2)
+            .with_separator("")
+            .with_case_styles(&[CaseStyle::Lower]);
+        let spawn = Spawn::new("sh", vec!["-c", TARGET], "correct");
+        let res = crack(
+            CrackParam::new(spawn, Box::from(['a']), 0, 0)
+                .with_attack(Attack::Passphrase(passphrase)),
+        )
+        .unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.combinations_total(), 9);
+    }
+
+    #[test]
+    fn test_crack_failure_pattern() {
+        // varying greeting on success, fixed banner on failure & nothing at all for "a"
+        let target = "while read l; do case \"$l\" in a) echo;; ab) echo \"hi $l\";; \
+                      *) echo 'Access denied';; esac; done";
+        let spawn = Spawn::new("sh", vec!["-c", target], "")
+            .with_failure_pattern(Matcher::literal("Access denied"));
+        let res = crack(CrackParam::new(spawn, Box::from(['a', 'b']), 0, 2)).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ab"));
+        assert_eq!(res.oracle_errors(), 1);
+    }
+}
@@ -1 +1,8 @@
-mod spawn;
+mod attempt;
+pub mod crack;
+mod oracle;
+pub(crate) mod spawn;
+
+pub use attempt::AttemptOracle;
+pub use crack::{crack, start};
+pub use oracle::ChildOracle;
@@ -0,0 +1,155 @@
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+    process::{Child, ChildStdin},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+};
+
+use log::warn;
+
+use crate::child::spawn::{child_spawn, read_lines};
+use crate::oracle::{Oracle, Response, SuccessCriterion, Verdict};
+use crate::parameters::Spawn;
+
+/// Written after every candidate, so that line based targets pick it up.
+const TERMINATOR: char = '\n';
+
+/// Oracle that keeps one child process per worker alive.
+/// Every candidate is written to the child's stdin, the next line on its stdout is the response.
+/// The child keeps running, so the response never carries an exit code or stderr;
+/// `start` rejects criteria that look at them.
+/// A child that doesn't answer within the spawn's timeout is killed & restarted
+/// for the next candidate, the candidate is reported as an oracle error.
+pub struct ChildOracle<I, S> {
+    spawn: Spawn<I, S>,
+    criterion: SuccessCriterion,
+    // None until the first candidate & after the child exited
+    process: Option<Process>,
+    // reused for every candidate, so that the loop doesn't allocate
+    input: String,
+}
+
+/// A running child with its pipes.
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    // lines from the reader thread, so that waiting for an answer can time out
+    lines: Receiver<String>,
+}
+
+impl Process {
+    fn start<I, S>(spawn: &Spawn<I, S>) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = S> + Clone,
+        S: AsRef<OsStr> + Clone,
+    {
+        let mut child = child_spawn(spawn)?;
+        let stdin = child.stdin.take().unwrap();
+        let lines = read_lines(child.stdout.take().unwrap());
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    /// Kills & reaps the child.
+    fn stop(mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl<I, S> ChildOracle<I, S>
+where
+    I: IntoIterator<Item = S> + Clone,
+    S: AsRef<OsStr> + Clone,
+{
+    /// Sends the current input & waits for the response line, starting a child if there's none.
+    /// If the child is gone before it answered or didn't answer in time, it's stopped.
+    fn exchange(&mut self) -> io::Result<Result<String, RecvTimeoutError>> {
+        let process = match &mut self.process {
+            Some(process) => process,
+            None => self.process.insert(Process::start(&self.spawn)?),
+        };
+        let answer = if process.stdin.write_all(self.input.as_bytes()).is_ok()
+            && process.stdin.flush().is_ok()
+        {
+            process.lines.recv_timeout(self.spawn.timeout())
+        } else {
+            Err(RecvTimeoutError::Disconnected)
+        };
+        if answer.is_err() {
+            self.process.take().unwrap().stop();
+        }
+        Ok(answer)
+    }
+}
+
+impl<I, S> Oracle for ChildOracle<I, S>
+where
+    I: IntoIterator<Item = S> + Clone + Send + Sync + 'static,
+    S: AsRef<OsStr> + Clone + Send + Sync + 'static,
+{
+    type Config = Spawn<I, S>;
+
+    /// The child starts with the first candidate.
+    fn create(config: &Self::Config, _tid: usize) -> Self {
+        Self {
+            spawn: config.clone(),
+            criterion: config.criterion().clone(),
+            process: None,
+            input: String::new(),
+        }
+    }
+
+    fn check(&mut self, candidate: &str) -> Verdict {
+        self.input.clear();
+        self.input.push_str(candidate);
+        self.input.push(TERMINATOR);
+
+        // The child may have exited without answering; give the candidate a second chance
+        // with a fresh process, so that it isn't skipped silently.
+        let answer = self.exchange().and_then(|answer| match answer {
+            Err(RecvTimeoutError::Disconnected) => {
+                warn!(
+                    "Child exited before answering \"{}\", restarting it.",
+                    candidate
+                );
+                self.exchange()
+            }
+            answer => Ok(answer),
+        });
+        let line = match answer {
+            Ok(Ok(line)) => line,
+            Ok(Err(RecvTimeoutError::Disconnected)) => {
+                return Verdict::Error(format!("child exited before answering \"{}\"", candidate))
+            }
+            Ok(Err(RecvTimeoutError::Timeout)) => {
+                let timeout = self.spawn.timeout();
+                warn!(
+                    "Child didn't answer \"{}\" within {:?}, restarting it.",
+                    candidate, timeout
+                );
+                return Verdict::Error(format!("no answer within {:?}", timeout));
+            }
+            Err(e) => return Verdict::Error(format!("child failed to spawn: {}", e)),
+        };
+
+        let response = Response {
+            // the child stopped writing before it finished the line
+            truncated: !line.ends_with('\n'),
+            ..Response::from_stdout(line)
+        };
+        self.criterion.judge(&response)
+    }
+
+    fn close(mut self) {
+        if let Some(mut process) = self.process.take() {
+            // closing stdin lets well behaved targets exit on their own
+            drop(process.stdin);
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+}
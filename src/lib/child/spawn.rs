@@ -1,15 +1,19 @@
 use std::{
     ffi::OsStr,
-    // io::BufReader,
-    process::{ChildStdin, ChildStdout, Command, Stdio},
+    io::{self, BufRead, BufReader},
+    process::{Child, ChildStdout, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
 };
 
+use crate::oracle::{Response, SuccessCriterion};
 use crate::parameters::Spawn;
 
-/// Attempts to spawn a child with the given script & args fields of the spawn struct.
-/// Returns false if program fails to spawn.
-/// Returns false if program outputs to stderr.
-/// Returns true if neither.
+/// Runs the program once with the given script & args fields of the spawn struct,
+/// without any input.
+/// Returns true if the program fails to spawn.
+/// Returns true if the program exits unsuccessfully or outputs to stderr.
+/// Returns false if neither.
 pub fn does_child_err<
     // I must implement IntoIterator with Item of type S & Clone
     I: IntoIterator<Item = S> + std::clone::Clone,
@@ -18,40 +22,57 @@ pub fn does_child_err<
 >(
     spawn: Spawn<I, S>,
 ) -> bool {
-    let mut child = Command::new(spawn.script())
+    let output = Command::new(spawn.script())
         .args(spawn.args().clone())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
         .stderr(Stdio::piped())
-        .spawn()
-        .expect("Child failed to spawn.");
+        .output();
 
-    match child.try_wait() {
-        Ok(None) => false,
-        Err(_) => false,
-        Ok(Some(_)) => {
-            // panic!(
-            // "{}",
-            // std::str::from_utf8(&child.wait_with_output().unwrap().stderr.into_boxed_slice())
-            //     .unwrap()
-            // )
-            panic!("Program errored.")
-        }
+    match output {
+        Err(_) => true,
+        Ok(output) => SuccessCriterion::ExitCode(0)
+            .and(SuccessCriterion::StderrEmpty)
+            .not()
+            .is_met(&Response::from(output)),
     }
 }
 
 /// Spawns a child with the given script & args fields of the spawn struct.
-/// Returns child's stdin & stdout.
+/// Stdin & stdout of the returned child are piped.
 pub fn child_spawn<
     I: IntoIterator<Item = S> + std::clone::Clone,
     S: AsRef<OsStr> + std::clone::Clone,
 >(
-    spawn: Spawn<I, S>,
-) -> (ChildStdin, ChildStdout) {
-    let mut child = Command::new(spawn.script())
+    spawn: &Spawn<I, S>,
+) -> io::Result<Child> {
+    Command::new(spawn.script())
         .args(spawn.args().clone())
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         // .stderr(Stdio::piped())
         .spawn()
-        .expect("Child failed to spawn");
-    (child.stdin.take().unwrap(), child.stdout.take().unwrap())
+}
+
+/// Forwards the lines of the child's stdout from a thread of their own, so that waiting
+/// for an answer can time out. The last line lacks the newline if the child stopped
+/// writing in the middle of it. The thread ends at EOF, i.e. once the child exits,
+/// or when the receiver is gone.
+pub(crate) fn read_lines(stdout: ChildStdout) -> Receiver<String> {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        loop {
+            let mut line = String::new();
+            match stdout.read_line(&mut line) {
+                Ok(n) if n > 0 => {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+                _ => break,
+            }
+        }
+    });
+    lines
 }
@@ -0,0 +1,101 @@
+use std::io;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+
+use thiserror::Error;
+
+use crate::engine::{
+    handle::{Control, CrackHandle, RunResult, Running},
+    internal::InternalCrackData,
+    result::CrackResult,
+    search::Search,
+    tasks::tasks,
+    workers::{self, Workers},
+};
+use crate::jni::TargetError;
+use crate::oracle::Oracle;
+use crate::symbols::KeyspaceOverflow;
+
+/// Why a crack didn't start.
+#[derive(Debug, Error)]
+pub enum CrackError {
+    #[error("invalid attack: {0}")]
+    Keyspace(#[from] KeyspaceOverflow),
+    #[error("can't read the wordlist: {0}")]
+    Wordlist(io::Error),
+    #[error("a crack needs at least one worker")]
+    NoWorkers,
+    #[error("invalid java target: {0}")]
+    Target(#[from] TargetError),
+    /// The criterion looks at something the responses never have, e.g. stderr for a
+    /// persistent child, so it would either always or never be met.
+    #[error("the criterion needs the {0}, which the responses of this target lack")]
+    Criterion(&'static str),
+}
+
+/// Cracks the password with any oracle, the backends build theirs from their params.
+/// Every worker thread creates its own oracle instance from `config`,
+/// for `Workers::Auto` the benchmark does so too.
+/// Fails if the attack's keyspace overflows, if its wordlist can't be read
+/// or for `Workers::Count(0)`.
+pub fn crack<O: Oracle>(search: Search, config: O::Config) -> Result<CrackResult, CrackError> {
+    Ok(start::<O>(search, config)?.wait())
+}
+
+/// Like `crack`, but returns right away, to cancel, pause or watch the workers.
+/// For `Workers::Auto` they start once the benchmark picked how many there are.
+pub fn start<O: Oracle>(search: Search, config: O::Config) -> Result<CrackHandle, CrackError> {
+    if search.worker_options().workers() == Workers::Count(0) {
+        return Err(CrackError::NoWorkers);
+    }
+    let param = InternalCrackData::try_from(search)?;
+    let not_started = param.progress();
+
+    // shared between all threads, so that they can look if one already found a solution
+    // or if they should pause
+    let control = Arc::new(Control::new());
+    let running = Running::default();
+    let runner = {
+        let (control, running) = (control.clone(), running.clone());
+        thread::spawn(move || run::<O>(param, config, control, running))
+    };
+    Ok(CrackHandle::new(running, not_started, control, runner))
+}
+
+/// Benchmarks the number of workers for `Workers::Auto`, then runs the workers
+/// & waits for them. The duration doesn't include the benchmark. After a cancel
+/// during the benchmark the workers stop before their first candidate.
+fn run<O: Oracle>(
+    mut param: InternalCrackData,
+    config: O::Config,
+    control: Arc<Control>,
+    running: Running,
+) -> RunResult {
+    let options = param.search().worker_options().clone();
+    if options.workers() == Workers::Auto {
+        // any candidate will do, only the time counts
+        let candidate = param
+            .search()
+            .nth_candidate(0)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let thread_count = workers::benchmark::<O>(
+            &config,
+            &candidate,
+            |tid| workers::setup(tid, options.cpu(tid), options.low_priority()),
+            || control.is_cancelled(),
+        );
+        param = param.with_thread_count(thread_count);
+    }
+    let param = Arc::new(param);
+    assert!(running.set(param.clone()).is_ok(), "Ran the workers twice");
+
+    let instant = Instant::now();
+    let results = tasks::<O>(param, Arc::from(config), control)
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+    (instant.elapsed().as_secs_f64(), results)
+}
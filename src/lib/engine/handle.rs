@@ -0,0 +1,210 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+
+use crate::attack::{Progress, RuleStats};
+use crate::engine::{internal::InternalCrackData, result::CrackResult, tasks::TaskResult};
+
+/// What the workers of a crack share: whether they should stop or wait.
+#[derive(Debug)]
+pub(crate) struct Control {
+    // a worker found the solution or the crack was cancelled
+    done: AtomicBool,
+    cancelled: AtomicBool,
+    pause: Mutex<Pause>,
+    resumed: Condvar,
+    // a worker got held or stopped
+    changed: Condvar,
+}
+
+/// How many workers run & how many of them the pause holds.
+#[derive(Debug, Default)]
+struct Pause {
+    paused: bool,
+    running: usize,
+    held: usize,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            pause: Mutex::default(),
+            resumed: Condvar::new(),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Tells every worker to stop at its next check.
+    pub fn finish(&self) {
+        self.done.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Counts `count` workers, before they get spawned.
+    pub fn spawned(&self, count: usize) {
+        self.pause.lock().unwrap().running += count;
+    }
+
+    /// Called by every worker as its last step.
+    pub fn stopped(&self) {
+        self.pause.lock().unwrap().running -= 1;
+        self.changed.notify_all();
+    }
+
+    /// Blocks the calling worker while the crack is paused.
+    pub fn wait_while_paused(&self) {
+        let mut pause = self.pause.lock().unwrap();
+        if !pause.paused {
+            return;
+        }
+        pause.held += 1;
+        self.changed.notify_all();
+        let mut pause = self.resumed.wait_while(pause, |p| p.paused).unwrap();
+        pause.held -= 1;
+    }
+
+    /// Blocks until the pause holds every running worker, or there is no pause.
+    fn wait_until_held(&self) {
+        let pause = self.pause.lock().unwrap();
+        drop(
+            self.changed
+                .wait_while(pause, |p| p.paused && p.held < p.running)
+                .unwrap(),
+        );
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.finish();
+        // paused workers have to wake up to stop
+        self.set_paused(false);
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.pause.lock().unwrap().paused = paused;
+        self.resumed.notify_all();
+        self.changed.notify_all();
+    }
+}
+
+/// The param of a crack, once the benchmark of `Workers::Auto` picked the number of workers.
+pub(crate) type Running = Arc<OnceLock<Arc<InternalCrackData>>>;
+
+/// What the thread that runs the workers reports back: how long they took & their results.
+pub(crate) type RunResult = (f64, Vec<TaskResult>);
+
+/// A crack running in the background, see `start`.
+/// Dropping the handle lets the crack run to its end, unobserved.
+pub struct CrackHandle {
+    running: Running,
+    // the progress until the workers run
+    not_started: Progress,
+    control: Arc<Control>,
+    // benchmarks for `Workers::Auto`, then runs & joins the workers
+    runner: thread::JoinHandle<RunResult>,
+}
+
+impl CrackHandle {
+    pub(crate) fn new(
+        running: Running,
+        not_started: Progress,
+        control: Arc<Control>,
+        runner: thread::JoinHandle<RunResult>,
+    ) -> Self {
+        Self {
+            running,
+            not_started,
+            control,
+            runner,
+        }
+    }
+
+    /// Stops every worker after its current candidate. `wait` then gives a failure,
+    /// unless a worker found the solution before.
+    pub fn cancel(&self) {
+        self.control.cancel();
+    }
+
+    /// Holds every worker after its current candidate, until `resume` or `cancel`.
+    /// The duration of the result includes the pauses.
+    pub fn pause(&self) {
+        self.control.set_paused(true);
+    }
+
+    pub fn resume(&self) {
+        self.control.set_paused(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.pause.lock().unwrap().paused
+    }
+
+    /// Blocks until `pause` holds every worker, so the progress stays put until `resume`.
+    /// Returns right away without a pause.
+    pub fn wait_until_paused(&self) {
+        self.control.wait_until_held();
+    }
+
+    /// Whether every worker stopped, i.e. `wait` won't block.
+    pub fn is_finished(&self) -> bool {
+        self.runner.is_finished()
+    }
+
+    /// How far all workers together got, counting the candidates the oracle judged.
+    /// Nothing is done yet while the benchmark of `Workers::Auto` runs.
+    pub fn progress(&self) -> Progress {
+        self.running
+            .get()
+            .map_or(self.not_started, |param| param.progress())
+    }
+
+    /// Blocks until every worker stopped & gives the result.
+    pub fn wait(self) -> CrackResult {
+        let (seconds, mut results) = self.runner.join().unwrap();
+        let candidates_tried = results.iter().map(|r| r.candidates_tried).sum();
+        let oracle_errors = results.iter().map(|r| r.oracle_errors).sum();
+        // every thread counts the same rules, in the same order
+        let mut rule_stats: Vec<RuleStats> = vec![];
+        for result in &results {
+            if rule_stats.is_empty() {
+                rule_stats.clone_from(&result.rule_stats);
+            } else {
+                rule_stats
+                    .iter_mut()
+                    .zip(&result.rule_stats)
+                    .for_each(|(total, stats)| total.add(stats));
+            }
+        }
+        let error = results.iter_mut().find_map(|r| r.error.take());
+        let solution = results
+            .into_iter()
+            .flat_map(|r| r.solution) // result of the Option<String> from the threads
+            .last(); // extract from the collection
+
+        let cancelled = self.control.is_cancelled();
+
+        let param = Arc::try_unwrap(self.running)
+            .ok()
+            .and_then(OnceLock::into_inner)
+            .and_then(|param| Arc::try_unwrap(param).ok())
+            .unwrap_or_else(|| panic!("There should only be one reference!"));
+        let result = if let Some(solution) = solution {
+            CrackResult::new_success(param, seconds, candidates_tried, oracle_errors, solution)
+        } else {
+            CrackResult::new_failure(param, seconds, candidates_tried, oracle_errors)
+        };
+        result
+            .with_rule_stats(rule_stats)
+            .with_cancelled(cancelled)
+            .with_error(error)
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use crate::attack::{Attack, BruteForce, Candidates, Progress};
+use crate::engine::queue::{self, Queue, Queued};
+use crate::engine::scheduler::{Chunked, ChunkedLines, Scheduler};
+use crate::engine::search::Search;
+use crate::engine::CrackError;
+
+#[derive(Debug)]
+pub(crate) struct InternalCrackData {
+    search: Search,
+    thread_count: usize,
+    total_combos: u128,
+    combos_per_thread: u128,
+    // how many workers there can be at most, candidates or wordlist bytes
+    parallelism: u128,
+    scheduler: Arc<Scheduler>,
+}
+
+impl InternalCrackData {
+    pub fn search(&self) -> &Search {
+        &self.search
+    }
+
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// 0 if the number of candidates isn't known upfront, e.g. for wordlists.
+    pub fn total_combos(&self) -> u128 {
+        self.total_combos
+    }
+
+    pub fn combos_per_thread(&self) -> u128 {
+        self.combos_per_thread
+    }
+
+    /// Replaces the thread count of the param's workers, e.g. with the benchmarked one.
+    pub fn with_thread_count(mut self, mut thread_count: usize) -> Self {
+        // Assuming that the user will never have thousands of CPUs
+        // there are so few possible permutations, that threading is unnecessary
+        if thread_count as u128 > self.parallelism {
+            thread_count = 1;
+        }
+        self.thread_count = thread_count;
+        self.combos_per_thread = self.total_combos / thread_count as u128;
+        self.scheduler = Arc::new(Scheduler::new(self.parallelism, thread_count));
+        self
+    }
+
+    /// The progress of all threads, counting the candidates the oracle judged.
+    pub fn progress(&self) -> Progress {
+        match self.search.attack() {
+            Attack::Wordlist(_) => self.scheduler.byte_progress(),
+            _ => self.scheduler.progress(),
+        }
+    }
+
+    /// The candidates of every thread, handed out in chunks by the scheduler: indices for
+    /// the attacks that can jump to one, bytes for wordlists. The candidates of a PCFG or
+    /// a Markov model can only be generated in order, one thread produces them for all others.
+    pub fn candidates(&self) -> Vec<Box<dyn Candidates>> {
+        let search = self.search();
+        let scheduler = &self.scheduler;
+        match search.attack() {
+            Attack::Wordlist(wordlist) => (0..self.thread_count)
+                .map(|tid| {
+                    Box::new(ChunkedLines::new(scheduler.clone(), tid, wordlist.clone()))
+                        as Box<dyn Candidates>
+                })
+                .collect(),
+            Attack::Markov(markov) => self.queued(queue::produce(markov.part(
+                search.charset(),
+                search.min_length(),
+                search.max_length(),
+                0,
+                1,
+            ))),
+            Attack::Pcfg(pcfg) => self.queued(queue::produce(pcfg.part(0, 1))),
+            _ => (0..self.thread_count)
+                .map(|tid| {
+                    let search = search.clone();
+                    Box::new(Chunked::new(scheduler.clone(), tid, move |offset| {
+                        indexed_part(&search, offset)
+                    })) as Box<dyn Candidates>
+                })
+                .collect(),
+        }
+    }
+
+    /// The candidates of every thread, taken from one queue.
+    fn queued(&self, queue: Queue) -> Vec<Box<dyn Candidates>> {
+        (0..self.thread_count)
+            .map(|tid| {
+                Box::new(Queued::new(queue.clone(), self.scheduler.clone(), tid))
+                    as Box<dyn Candidates>
+            })
+            .collect()
+    }
+}
+
+/// The candidates of an attack with a keyspace, from `offset` on.
+fn indexed_part(search: &Search, offset: u128) -> Box<dyn Candidates> {
+    let (charset, min_length, max_length) =
+        (search.charset(), search.min_length(), search.max_length());
+    match search.attack() {
+        Attack::BruteForce => Box::new(BruteForce::new(charset, min_length, max_length, offset, 1)),
+        Attack::Mask(mask) => Box::new(mask.part(offset, 1)),
+        Attack::Hybrid(hybrid) => Box::new(hybrid.part(offset, 1)),
+        Attack::Combinator(combinator) => Box::new(combinator.part(offset, 1)),
+        Attack::Passphrase(passphrase) => Box::new(passphrase.part(offset, 1)),
+        Attack::Wordlist(_) | Attack::Markov(_) | Attack::Pcfg(_) => {
+            unreachable!("not scheduled by index")
+        }
+    }
+}
+
+impl TryFrom<Search> for InternalCrackData {
+    type Error = CrackError;
+
+    fn try_from(search: Search) -> Result<Self, Self::Error> {
+        // 0 for wordlists
+        let total_combos = search
+            .attack()
+            .keyspace(search.charset(), search.min_length(), search.max_length())?
+            .unwrap_or(0);
+        let parallelism = match search.attack() {
+            // split by bytes, as the lines aren't counted upfront
+            Attack::Wordlist(wordlist) => wordlist.len().map_err(CrackError::Wordlist)?.into(),
+            _ => total_combos,
+        };
+        let thread_count = search.worker_options().workers().count();
+        Ok(Self {
+            search,
+            thread_count: 0,
+            total_combos,
+            combos_per_thread: 0,
+            parallelism,
+            scheduler: Arc::new(Scheduler::new(0, 0)),
+        }
+        .with_thread_count(thread_count))
+    }
+}
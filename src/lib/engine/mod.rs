@@ -0,0 +1,15 @@
+mod crack;
+mod handle;
+mod internal;
+mod queue;
+mod result;
+mod scheduler;
+mod search;
+mod tasks;
+mod workers;
+
+pub use crack::{crack, start, CrackError};
+pub use handle::CrackHandle;
+pub use result::CrackResult;
+pub use search::Search;
+pub use workers::{WorkerOptions, Workers};
@@ -0,0 +1,115 @@
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::attack::{Candidates, Progress};
+use crate::engine::scheduler::Scheduler;
+
+/// How many candidates the producer of a queue may be ahead of the workers.
+const QUEUE_LENGTH: usize = 1024;
+
+/// Candidates that can only be generated in order, e.g. those of a PCFG, which are
+/// produced by one thread & taken by whichever worker is free.
+pub(crate) type Queue = Arc<Mutex<Receiver<String>>>;
+
+/// Starts a thread producing `candidates` into a new queue. It stops once they're through
+/// or every worker dropped the queue.
+pub(crate) fn produce(mut candidates: impl Candidates + 'static) -> Queue {
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_LENGTH);
+    thread::spawn(move || {
+        let mut buf = String::new();
+        while candidates.next(&mut buf) {
+            if sender.send(buf.clone()).is_err() {
+                break;
+            }
+        }
+    });
+    Arc::new(Mutex::new(receiver))
+}
+
+/// The candidates of one worker, taken from a queue. The scheduler only counts them.
+pub(crate) struct Queued {
+    queue: Queue,
+    scheduler: Arc<Scheduler>,
+    worker: usize,
+    // the last candidate is taken, but not checked yet
+    pending: bool,
+}
+
+impl Queued {
+    pub fn new(queue: Queue, scheduler: Arc<Scheduler>, worker: usize) -> Self {
+        Self {
+            queue,
+            scheduler,
+            worker,
+            pending: false,
+        }
+    }
+}
+
+impl Candidates for Queued {
+    fn next(&mut self, buf: &mut String) -> bool {
+        let Ok(candidate) = self.queue.lock().unwrap().recv() else {
+            return false;
+        };
+        *buf = candidate;
+        self.pending = true;
+        true
+    }
+
+    fn checked(&mut self) {
+        if std::mem::take(&mut self.pending) {
+            self.scheduler.checked(self.worker, 1, 1);
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        self.scheduler.progress()
+    }
+}
+
+#[cfg(test)]
+mod tests_queue {
+    use super::*;
+    use crate::attack::{Pcfg, PcfgModel};
+
+    #[test]
+    fn test_workers_take_every_candidate_once() {
+        let model = PcfgModel::parse(
+            "structure L1D1L1 2\nstructure D1 1\n\
+             terminal L1 2 a\nterminal L1 1 b\nterminal L1 1 c\n\
+             terminal D1 3 1\nterminal D1 1 2",
+        )
+        .unwrap();
+        let pcfg = Pcfg::new(&model);
+        let total = pcfg.keyspace().unwrap();
+        let scheduler = Arc::new(Scheduler::new(total, 3));
+        let queue = produce(pcfg.part(0, 1));
+        let handles = (0..3)
+            .map(|worker| {
+                let mut queued = Queued::new(queue.clone(), scheduler.clone(), worker);
+                thread::spawn(move || {
+                    let mut candidates = vec![];
+                    let mut buf = String::new();
+                    while queued.next(&mut buf) {
+                        candidates.push(buf.clone());
+                        queued.checked();
+                    }
+                    candidates
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut candidates = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        candidates.sort();
+        let mut expected = (0..total).map(|i| pcfg.nth(i).unwrap()).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(candidates, expected);
+        assert_eq!(
+            scheduler.progress(),
+            Progress::Candidates { done: total, total }
+        );
+    }
+}
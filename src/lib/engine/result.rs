@@ -0,0 +1,141 @@
+use std::io;
+
+use crate::attack::RuleStats;
+use crate::engine::internal::InternalCrackData;
+
+pub struct CrackResult {
+    solution: Option<String>,
+    thread_count: usize,
+    combinations_total: u128,
+    combinations_per_thread: u128,
+    duration_in_seconds: f64,
+    candidates_tried: u128,
+    oracle_errors: usize,
+    rule_stats: Vec<RuleStats>,
+    cancelled: bool,
+    error: Option<io::Error>,
+}
+
+impl CrackResult {
+    fn new(
+        cp: InternalCrackData,
+        duration_in_seconds: f64,
+        candidates_tried: u128,
+        oracle_errors: usize,
+        solution: Option<String>,
+    ) -> Self {
+        Self {
+            solution,
+            thread_count: cp.thread_count(),
+            combinations_total: cp.total_combos(),
+            combinations_per_thread: cp.combos_per_thread(),
+            duration_in_seconds,
+            candidates_tried,
+            oracle_errors,
+            rule_stats: vec![],
+            cancelled: false,
+            error: None,
+        }
+    }
+
+    pub(crate) fn new_failure(
+        cp: InternalCrackData,
+        seconds_as_fraction: f64,
+        candidates_tried: u128,
+        oracle_errors: usize,
+    ) -> Self {
+        Self::new(
+            cp,
+            seconds_as_fraction,
+            candidates_tried,
+            oracle_errors,
+            None,
+        )
+    }
+
+    pub(crate) fn new_success(
+        cp: InternalCrackData,
+        seconds_as_fraction: f64,
+        candidates_tried: u128,
+        oracle_errors: usize,
+        solution: String,
+    ) -> Self {
+        Self::new(
+            cp,
+            seconds_as_fraction,
+            candidates_tried,
+            oracle_errors,
+            Some(solution),
+        )
+    }
+
+    pub(crate) fn with_rule_stats(mut self, rule_stats: Vec<RuleStats>) -> Self {
+        self.rule_stats = rule_stats;
+        self
+    }
+
+    pub(crate) fn with_cancelled(mut self, cancelled: bool) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    pub(crate) fn with_error(mut self, error: Option<io::Error>) -> Self {
+        self.error = error;
+        self
+    }
+
+    pub const fn is_failure(&self) -> bool {
+        self.solution.is_none()
+    }
+
+    pub const fn is_success(&self) -> bool {
+        self.solution.is_some()
+    }
+
+    pub const fn solution(&self) -> &Option<String> {
+        &self.solution
+    }
+
+    pub const fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// 0 if the number of candidates isn't known upfront, e.g. for wordlists.
+    pub const fn combinations_total(&self) -> u128 {
+        self.combinations_total
+    }
+
+    pub const fn combinations_per_thread(&self) -> u128 {
+        self.combinations_per_thread
+    }
+
+    pub const fn duration_in_seconds(&self) -> f64 {
+        self.duration_in_seconds
+    }
+
+    /// Candidates all threads checked until the solution was found or the attack was exhausted.
+    pub const fn candidates_tried(&self) -> u128 {
+        self.candidates_tried
+    }
+
+    /// Candidates the oracle couldn't judge, e.g. because the response was empty.
+    pub const fn oracle_errors(&self) -> usize {
+        self.oracle_errors
+    }
+
+    /// Whether the crack was cancelled through its handle before it was through.
+    pub const fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Why a worker stopped before its candidates were through, e.g. the wordlist became
+    /// unreadable. A failure then doesn't mean that none of the candidates is the password.
+    pub const fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// How every rule did, in the order of the rule file. Empty without rules.
+    pub fn rule_stats(&self) -> &[RuleStats] {
+        &self.rule_stats
+    }
+}
@@ -0,0 +1,531 @@
+use std::io;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+use crate::attack::{Candidates, Progress, RuleStats, Wordlist, WordlistPart};
+
+/// How long a chunk should keep a worker busy, at the rate it checked its last one.
+const CHUNK_DURATION: Duration = Duration::from_millis(500);
+
+/// The first chunk of a wordlist, in bytes, as a single byte rarely holds a whole line.
+const FIRST_WORDLIST_CHUNK: u128 = 4096;
+
+/// Hands out contiguous chunks of the candidate indices, or of the bytes of a wordlist,
+/// to the workers.
+///
+/// Chunks come from a shared cursor & get smaller towards the end of the keyspace,
+/// so that no worker is left with a big chunk while the others are idle. Once the cursor
+/// is through, idle workers steal the back half of the biggest range another worker is
+/// still on. Every index is handed out exactly once.
+#[derive(Debug)]
+pub(crate) struct Scheduler {
+    total: u128,
+    // there's no stable AtomicU128, the lock is only taken once per chunk
+    cursor: Mutex<u128>,
+    // what each worker is on, locked by the worker for every candidate & by thieves
+    slots: Box<[Mutex<Slot>]>,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    range: Range<u128>,
+    // indices or bytes the worker is through with, over all of its chunks
+    done: u128,
+    // the candidates or words among them
+    items: u64,
+}
+
+impl Scheduler {
+    pub fn new(total: u128, workers: usize) -> Self {
+        Self {
+            total,
+            cursor: Mutex::new(0),
+            slots: (0..workers).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    /// Progress of all workers together.
+    pub fn progress(&self) -> Progress {
+        Progress::Candidates {
+            done: self.sum(|slot| slot.done),
+            total: self.total,
+        }
+    }
+
+    /// Progress of all workers together, if the scheduler hands out the bytes of a wordlist.
+    pub fn byte_progress(&self) -> Progress {
+        Progress::Bytes {
+            done: self.sum(|slot| slot.done) as u64,
+            total: self.total as u64,
+            lines: self.sum(|slot| slot.items.into()) as u64,
+        }
+    }
+
+    fn sum(&self, field: impl Fn(&Slot) -> u128) -> u128 {
+        self.slots
+            .iter()
+            .map(|slot| field(&slot.lock().unwrap()))
+            .sum()
+    }
+
+    /// Takes the next index of the worker's range, None once it's through or stolen.
+    fn claim(&self, worker: usize) -> Option<u128> {
+        self.slots[worker].lock().unwrap().range.next()
+    }
+
+    /// Runs `claim` on the worker's range, thieves wait until it's done.
+    fn claim_with<T>(&self, worker: usize, claim: impl FnOnce(&mut Range<u128>) -> T) -> T {
+        claim(&mut self.slots[worker].lock().unwrap().range)
+    }
+
+    /// Counts `units` indices or bytes with `items` candidates or words among them as done,
+    /// once the oracle judged them.
+    pub fn checked(&self, worker: usize, units: u128, items: u64) {
+        let mut slot = self.slots[worker].lock().unwrap();
+        slot.done += units;
+        slot.items += items;
+    }
+
+    /// Gives the worker a new range of up to `size` indices. What's left of its last range
+    /// holds no candidates, e.g. the bytes after the last line of a wordlist's chunk,
+    /// & counts as done. None once every index is handed out & there's nothing left
+    /// worth stealing.
+    fn chunk(&self, worker: usize, size: u128) -> Option<Range<u128>> {
+        {
+            let mut slot = self.slots[worker].lock().unwrap();
+            slot.done += slot.range.end - slot.range.start;
+            slot.range = 0..0;
+        }
+        let range = self.next_chunk(size).or_else(|| self.steal(worker))?;
+        self.slots[worker].lock().unwrap().range = range.clone();
+        Some(range)
+    }
+
+    fn next_chunk(&self, size: u128) -> Option<Range<u128>> {
+        let mut cursor = self.cursor.lock().unwrap();
+        let remaining = self.total - *cursor;
+        if remaining == 0 {
+            return None;
+        }
+        // at most half of an even share of the rest, so that the last chunks are small
+        let share = remaining / (2 * self.slots.len() as u128);
+        let size = size.min(share).max(1);
+        let start = *cursor;
+        *cursor += size;
+        Some(start..*cursor)
+    }
+
+    /// Cuts the biggest range of the other workers in half & takes the back half.
+    fn steal(&self, thief: usize) -> Option<Range<u128>> {
+        loop {
+            let victim = (0..self.slots.len())
+                .filter(|worker| *worker != thief)
+                .map(|worker| (worker, self.slots[worker].lock().unwrap().range.clone()))
+                .max_by_key(|(_, range)| range.end - range.start)
+                .filter(|(_, range)| range.end - range.start >= 2)?
+                .0;
+            let mut slot = self.slots[victim].lock().unwrap();
+            let range = &mut slot.range;
+            // the victim went on in the meantime, look again
+            if range.end - range.start < 2 {
+                continue;
+            }
+            let middle = range.start + (range.end - range.start) / 2;
+            let stolen = middle..range.end;
+            range.end = middle;
+            return Some(stolen);
+        }
+    }
+}
+
+/// Sizes the chunks of a worker by how long its last one took.
+struct ChunkSize {
+    chunk_start: Instant,
+    chunk_done: u128,
+    size: u128,
+}
+
+impl ChunkSize {
+    fn new(first: u128) -> Self {
+        Self {
+            chunk_start: Instant::now(),
+            chunk_done: 0,
+            size: first,
+        }
+    }
+
+    /// Sizes the next chunk so that it takes about `CHUNK_DURATION` at the last one's rate.
+    fn next(&mut self) -> u128 {
+        let seconds = self.chunk_start.elapsed().as_secs_f64();
+        if self.chunk_done > 0 && seconds > 0.0 {
+            let rate = self.chunk_done as f64 / seconds;
+            // saturates for absurd rates
+            self.size = ((rate * CHUNK_DURATION.as_secs_f64()) as u128).max(1);
+        }
+        self.chunk_start = Instant::now();
+        self.chunk_done = 0;
+        self.size
+    }
+}
+
+/// The candidates of one worker, in chunks of the scheduler's indices.
+/// `part` gives the candidates from an index on, like the parts of the attacks with step 1.
+pub(crate) struct Chunked<F> {
+    scheduler: Arc<Scheduler>,
+    worker: usize,
+    part: F,
+    candidates: Option<Box<dyn Candidates>>,
+    size: ChunkSize,
+    // the last candidate is claimed, but not checked yet
+    pending: bool,
+}
+
+impl<F> Chunked<F>
+where
+    F: Fn(u128) -> Box<dyn Candidates> + Send,
+{
+    pub fn new(scheduler: Arc<Scheduler>, worker: usize, part: F) -> Self {
+        Self {
+            scheduler,
+            worker,
+            part,
+            candidates: None,
+            // the rate isn't known yet, the first chunk is a single candidate
+            size: ChunkSize::new(1),
+            pending: false,
+        }
+    }
+}
+
+impl<F> Candidates for Chunked<F>
+where
+    F: Fn(u128) -> Box<dyn Candidates> + Send,
+{
+    fn next(&mut self, buf: &mut String) -> bool {
+        loop {
+            if let Some(candidates) = &mut self.candidates {
+                // the candidates are at the claimed index, as the range is only ever cut at the end
+                if self.scheduler.claim(self.worker).is_some() {
+                    self.size.chunk_done += 1;
+                    self.pending = true;
+                    return candidates.next(buf);
+                }
+            }
+            let size = self.size.next();
+            let Some(range) = self.scheduler.chunk(self.worker, size) else {
+                return false;
+            };
+            self.candidates = Some((self.part)(range.start));
+        }
+    }
+
+    fn checked(&mut self) {
+        if std::mem::take(&mut self.pending) {
+            self.scheduler.checked(self.worker, 1, 1);
+        }
+    }
+
+    fn hit(&mut self) {
+        if let Some(candidates) = &mut self.candidates {
+            candidates.hit();
+        }
+    }
+
+    fn progress(&self) -> Progress {
+        self.scheduler.progress()
+    }
+}
+
+/// The words of one worker, in chunks of the scheduler's bytes of the wordlist.
+///
+/// A line belongs to the chunk its first byte is in, like with `Wordlist::open_part`.
+/// The worker claims every line before it reads it, so that a thief can't take it
+/// at the same time.
+pub(crate) struct ChunkedLines {
+    scheduler: Arc<Scheduler>,
+    worker: usize,
+    wordlist: Wordlist,
+    part: Option<WordlistPart>,
+    // of the parts of the chunks before
+    stats: Vec<RuleStats>,
+    size: ChunkSize,
+    // the bytes of the current word's line, counted once all of its candidates are checked
+    pending: u128,
+    error: Option<io::Error>,
+}
+
+impl ChunkedLines {
+    pub fn new(scheduler: Arc<Scheduler>, worker: usize, wordlist: Wordlist) -> Self {
+        Self {
+            scheduler,
+            worker,
+            stats: wordlist
+                .rules()
+                .rules()
+                .iter()
+                .map(RuleStats::new)
+                .collect(),
+            wordlist,
+            part: None,
+            size: ChunkSize::new(FIRST_WORDLIST_CHUNK),
+            pending: 0,
+            error: None,
+        }
+    }
+
+    /// The previous word is checked with every rule.
+    fn checked_word(&mut self) {
+        let bytes = std::mem::take(&mut self.pending);
+        if bytes > 0 {
+            self.scheduler.checked(self.worker, bytes, 1);
+        }
+    }
+
+    /// Logs & keeps why the worker stops, see `take_error`.
+    fn unreadable(&mut self, e: io::Error) -> bool {
+        warn!(
+            "Worker {:>2} can't read wordlist {}: {}",
+            self.worker,
+            self.wordlist.path().display(),
+            e
+        );
+        self.error = Some(e);
+        false
+    }
+
+    fn close_part(&mut self) {
+        if let Some(part) = self.part.take() {
+            self.stats
+                .iter_mut()
+                .zip(part.rule_stats())
+                .for_each(|(total, stats)| total.add(&stats));
+        }
+    }
+}
+
+impl Candidates for ChunkedLines {
+    fn next(&mut self, buf: &mut String) -> bool {
+        loop {
+            if let Some(part) = &mut self.part {
+                let lines = part.lines();
+                let claimed = self.scheduler.claim_with(self.worker, |range| {
+                    part.limit(range.end as u64);
+                    if !part.next(buf) {
+                        return None;
+                    }
+                    if part.lines() == lines {
+                        // another rule on the same word
+                        return Some(0);
+                    }
+                    // the new line with the skipped ones before it, up to where the range ends
+                    let end = u128::from(part.pos()).min(range.end);
+                    let bytes = end - range.start;
+                    range.start = end;
+                    Some(bytes)
+                });
+                match claimed {
+                    Some(0) => return true,
+                    Some(bytes) => {
+                        self.checked_word();
+                        self.pending = bytes;
+                        self.size.chunk_done += bytes;
+                        return true;
+                    }
+                    None => {}
+                }
+            }
+            // through with the chunk, or the rest of it was stolen
+            self.checked_word();
+            if let Some(Err(e)) = self.part.as_mut().map(WordlistPart::take_error) {
+                self.close_part();
+                return self.unreadable(e);
+            }
+            self.close_part();
+            let size = self.size.next();
+            let Some(range) = self.scheduler.chunk(self.worker, size) else {
+                return false;
+            };
+            match self
+                .wordlist
+                .open_range(range.start as u64, range.end as u64)
+            {
+                Ok(part) => self.part = Some(part),
+                Err(e) => return self.unreadable(e),
+            }
+        }
+    }
+
+    fn hit(&mut self) {
+        if let Some(part) = &mut self.part {
+            part.hit();
+        }
+    }
+
+    fn rule_stats(&self) -> Vec<RuleStats> {
+        let mut stats = self.stats.clone();
+        if let Some(part) = &self.part {
+            stats
+                .iter_mut()
+                .zip(part.rule_stats())
+                .for_each(|(total, stats)| total.add(&stats));
+        }
+        stats
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    fn progress(&self) -> Progress {
+        self.scheduler.byte_progress()
+    }
+}
+
+#[cfg(test)]
+mod tests_scheduler {
+    use std::thread;
+
+    use super::*;
+    use crate::attack::tests_attack::TempFile;
+    use crate::attack::{Mask, Rules};
+
+    #[test]
+    fn test_steal() {
+        let scheduler = Scheduler::new(100, 2);
+        // half of an even share of the rest at most
+        assert_eq!(scheduler.chunk(0, 1000), Some(0..25));
+        assert_eq!(scheduler.chunk(1, 1000), Some(25..43));
+        assert_eq!(scheduler.claim(0), Some(0));
+        *scheduler.cursor.lock().unwrap() = 100;
+
+        // worker 1 is through with its range & steals the back half of worker 0's
+        scheduler.slots[1].lock().unwrap().range = 43..43;
+        assert_eq!(scheduler.chunk(1, 1000), Some(13..25));
+        assert_eq!(scheduler.slots[0].lock().unwrap().range, 1..13);
+        // nothing is checked yet
+        assert_eq!(
+            scheduler.progress(),
+            Progress::Candidates {
+                done: 0,
+                total: 100
+            }
+        );
+    }
+
+    /// Runs `candidates` on a thread each, the first one checks slowly,
+    /// so that the others steal its work. Gives all candidates, sorted,
+    /// & how many candidates each rule produced.
+    fn check_all(candidates: Vec<Box<dyn Candidates>>) -> (Vec<String>, Vec<usize>) {
+        let handles = candidates
+            .into_iter()
+            .enumerate()
+            .map(|(worker, mut candidates)| {
+                thread::spawn(move || {
+                    let mut checked = vec![];
+                    let mut buf = String::new();
+                    while candidates.next(&mut buf) {
+                        if worker == 0 {
+                            thread::sleep(Duration::from_millis(1));
+                        }
+                        checked.push(buf.clone());
+                        candidates.checked();
+                    }
+                    (checked, candidates.rule_stats())
+                })
+            })
+            .collect::<Vec<_>>();
+        let (mut checked, mut per_rule) = (vec![], vec![]);
+        for handle in handles {
+            let (candidates, stats) = handle.join().unwrap();
+            checked.extend(candidates);
+            per_rule.resize(stats.len(), 0);
+            for (sum, stats) in per_rule.iter_mut().zip(&stats) {
+                *sum += stats.candidates();
+            }
+        }
+        checked.sort();
+        (checked, per_rule)
+    }
+
+    #[test]
+    fn test_workers_check_every_candidate_once() {
+        let mask = Mask::with_classes("?1?d?d", &[&['a', 'b', 'c']]).unwrap();
+        let total = mask.keyspace().unwrap();
+        let scheduler = Arc::new(Scheduler::new(total, 3));
+        let candidates = (0..3)
+            .map(|worker| {
+                let mask = mask.clone();
+                Box::new(Chunked::new(scheduler.clone(), worker, move |offset| {
+                    Box::new(mask.part(offset, 1)) as Box<dyn Candidates>
+                })) as Box<dyn Candidates>
+            })
+            .collect();
+        let mut buf = String::new();
+        let expected = (0..total)
+            .map(|i| {
+                mask.nth(i, &mut buf);
+                buf.clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(check_all(candidates).0, expected);
+        assert_eq!(
+            scheduler.progress(),
+            Progress::Candidates { done: total, total }
+        );
+    }
+
+    #[test]
+    fn test_workers_read_every_line_once() {
+        let words = (0..2000).map(|i| format!("word{}", i)).collect::<Vec<_>>();
+        // comments & empty lines count as bytes, but not as lines
+        let file = TempFile::new("chunked", format!("# words\n{}\n\n", words.join("\n")));
+        let wordlist = file.wordlist().with_rules(Rules::parse(":\nu").unwrap());
+        let len = wordlist.len().unwrap();
+        let scheduler = Arc::new(Scheduler::new(len.into(), 3));
+        let candidates = (0..3)
+            .map(|worker| {
+                Box::new(ChunkedLines::new(
+                    scheduler.clone(),
+                    worker,
+                    wordlist.clone(),
+                )) as Box<dyn Candidates>
+            })
+            .collect();
+        let (checked, per_rule) = check_all(candidates);
+
+        let mut expected = words
+            .iter()
+            .flat_map(|word| [word.clone(), word.to_uppercase()])
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(checked, expected);
+        // no rule stats are lost between the chunks
+        assert_eq!(per_rule, [words.len(), words.len()]);
+        assert_eq!(
+            scheduler.byte_progress(),
+            Progress::Bytes {
+                done: len,
+                total: len,
+                lines: words.len() as u64
+            }
+        );
+    }
+
+    #[test]
+    fn test_unreadable_wordlist_error() {
+        let file = TempFile::new("unreadable", "foo\nbar\n");
+        let wordlist = file.wordlist();
+        let scheduler = Arc::new(Scheduler::new(wordlist.len().unwrap().into(), 1));
+        let mut candidates = ChunkedLines::new(scheduler, 0, wordlist);
+        // gone before the first chunk is opened
+        drop(file);
+        assert!(!candidates.next(&mut String::new()));
+        assert_eq!(
+            candidates.take_error().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+}
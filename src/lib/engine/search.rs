@@ -0,0 +1,80 @@
+use std::io;
+
+use crate::attack::Attack;
+use crate::engine::workers::WorkerOptions;
+use crate::symbols::{combination_count, KeyspaceOverflow};
+
+/// What to search through & with how many workers, the same for every kind of target.
+#[derive(Debug, Clone)]
+pub struct Search {
+    charset: Box<[char]>,
+    min_length: u8,
+    max_length: u8,
+    attack: Attack,
+    worker_options: WorkerOptions,
+}
+
+impl Search {
+    /// By default a brute force over the charset.
+    pub fn new(charset: Box<[char]>, min_length: u8, max_length: u8) -> Self {
+        if min_length > max_length {
+            panic!("min length must be <= max length")
+        }
+        Self {
+            charset,
+            min_length,
+            max_length,
+            attack: Attack::default(),
+            worker_options: WorkerOptions::default(),
+        }
+    }
+
+    /// Replaces the brute force over the charset, e.g. with a wordlist.
+    pub fn with_attack(mut self, attack: Attack) -> Self {
+        self.attack = attack;
+        self
+    }
+
+    /// Replaces the default of one worker per CPU but one, neither pinned nor at low priority.
+    pub fn with_worker_options(mut self, worker_options: WorkerOptions) -> Self {
+        self.worker_options = worker_options;
+        self
+    }
+
+    pub const fn charset(&self) -> &[char] {
+        &self.charset
+    }
+
+    pub const fn max_length(&self) -> u8 {
+        self.max_length
+    }
+
+    pub const fn min_length(&self) -> u8 {
+        self.min_length
+    }
+
+    /// Number of combinations of the charset between the min & max length.
+    pub fn total_combos(&self) -> Result<u128, KeyspaceOverflow> {
+        combination_count(&self.charset, self.min_length, self.max_length)
+    }
+
+    /// The candidate at index `n` of the attack, see `Attack::nth_candidate`.
+    pub fn nth_candidate(&self, n: u128) -> io::Result<Option<String>> {
+        self.attack
+            .nth_candidate(&self.charset, self.min_length, self.max_length, n)
+    }
+
+    /// The index of `candidate` in the attack, see `Attack::candidate_index`.
+    pub fn candidate_index(&self, candidate: &str) -> io::Result<Option<u128>> {
+        self.attack
+            .candidate_index(&self.charset, self.min_length, self.max_length, candidate)
+    }
+
+    pub fn attack(&self) -> &Attack {
+        &self.attack
+    }
+
+    pub fn worker_options(&self) -> &WorkerOptions {
+        &self.worker_options
+    }
+}
@@ -0,0 +1,158 @@
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+use log::{info, trace, warn};
+
+use crate::attack::{Candidates, RuleStats};
+use crate::engine::handle::Control;
+use crate::engine::internal::InternalCrackData;
+use crate::engine::workers;
+use crate::oracle::{Oracle, Verdict};
+
+/// What a single worker thread reports back.
+pub(crate) struct TaskResult {
+    pub solution: Option<String>,
+    pub candidates_tried: u128,
+    /// Candidates the oracle couldn't judge.
+    pub oracle_errors: usize,
+    pub rule_stats: Vec<RuleStats>,
+    /// Why the worker stopped before its candidates were through.
+    pub error: Option<io::Error>,
+}
+
+pub(crate) fn tasks<O: Oracle>(
+    params: Arc<InternalCrackData>,
+    config: Arc<O::Config>,
+    control: Arc<Control>,
+) -> Vec<thread::JoinHandle<TaskResult>> {
+    let mut handles = vec![];
+    let candidates = params.candidates();
+    // counted up front, so that a pause waits for the workers that didn't start yet
+    control.spawned(candidates.len());
+    // spawn thread for each cpu, with its share of the candidates
+    for (tid, candidates) in candidates.into_iter().enumerate() {
+        let options = params.search().worker_options();
+        let setup = (options.cpu(tid), options.low_priority());
+
+        handles.push(task::<O>(
+            config.clone(),
+            control.clone(),
+            candidates,
+            tid,
+            setup,
+        ));
+    }
+    handles
+}
+
+fn task<O: Oracle>(
+    config: Arc<O::Config>,
+    control: Arc<Control>,
+    mut candidates: Box<dyn Candidates>,
+    tid: usize,
+    (cpu, low_priority): (Option<usize>, bool),
+) -> thread::JoinHandle<TaskResult> {
+    // Counter for total iterations/total checked values
+    let mut iteration_count: u128 = 0;
+
+    thread::spawn(move || {
+        workers::setup(tid, cpu, low_priority);
+        let mut oracle = O::create(&config, tid);
+
+        // reused for every candidate, so that the loop doesn't allocate
+        let mut current_crack_string = String::new();
+
+        // The result that the thread calculated/found
+        let mut result = None;
+        let mut oracle_errors = 0;
+
+        /// The amount of iterations after the thread checks if another thread
+        /// is already done, so that we can stop further work. We do this only after
+        /// a few millions iterations to keep the overhead low. Tests on my machine
+        /// (i5-10600K) showed that 2 million iterations take about 1s - this should be okay
+        /// because the overhead is not that big. A test already showed that
+        /// increasing this has no real impact on the iterations per s.
+        const INTERRUPT_COUNT_THRESHOLD: usize = 10;
+        let mut interrupt_count = INTERRUPT_COUNT_THRESHOLD;
+
+        loop {
+            // tell about progress + stop if another thread found a solution
+            {
+                if interrupt_count == 0 {
+                    interrupt_count = INTERRUPT_COUNT_THRESHOLD;
+                    if control.is_done() {
+                        trace!("Thread {:>2} stops at {} progress because another thread found a solution", tid, candidates.progress());
+                        break;
+                    } else {
+                        trace!("Thread {:>2} is at {} progress", tid, candidates.progress());
+                    }
+                }
+                interrupt_count -= 1;
+            }
+
+            // cheap enough for every candidate, so that pauses & cancels are quick
+            // even with slow oracles
+            {
+                control.wait_while_paused();
+                if control.is_cancelled() {
+                    info!(
+                        "Thread {:>2} stops at {} progress because the crack was cancelled",
+                        tid,
+                        candidates.progress()
+                    );
+                    break;
+                }
+            }
+
+            // the actual cracking
+            {
+                if !candidates.next(&mut current_crack_string) {
+                    info!(
+                        "Thread {:>2} checked all possible values without finding a solution. Done.",
+                        tid
+                    );
+                    break;
+                }
+
+                iteration_count += 1;
+
+                let verdict = oracle.check(&current_crack_string);
+                candidates.checked();
+                match verdict {
+                    Verdict::Correct => {
+                        info!(
+                            "Thread {:>2} found solution \"{}\" at a progress of {}!",
+                            tid,
+                            current_crack_string,
+                            candidates.progress()
+                        );
+                        candidates.hit();
+                        // let other threads know we are done
+                        control.finish();
+                        result = Some(current_crack_string);
+                        break;
+                    }
+                    Verdict::Incorrect => {}
+                    Verdict::Error(reason) => {
+                        warn!(
+                            "Thread {:>2} couldn't check \"{}\": {}",
+                            tid, current_crack_string, reason
+                        );
+                        oracle_errors += 1;
+                    }
+                }
+            }
+        }
+
+        oracle.close();
+        control.stopped();
+        TaskResult {
+            solution: result,
+            candidates_tried: iteration_count,
+            oracle_errors,
+            rule_stats: candidates.rule_stats(),
+            error: candidates.take_error(),
+        }
+    })
+}
@@ -0,0 +1,277 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+use crate::oracle::Oracle;
+
+/// How many worker threads a crack uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Workers {
+    /// One per CPU, but one of them is left to the rest of the system.
+    #[default]
+    Cpus,
+    /// Exactly this many, e.g. several per CPU for targets that mostly wait on IO,
+    /// or just a couple on a shared machine.
+    Count(usize),
+    /// As many as a quick benchmark with the oracle finds worthwhile, see `benchmark`.
+    Auto,
+}
+
+impl Workers {
+    /// The number of workers, the benchmark starts from 1 for `Auto`.
+    pub(crate) fn count(self) -> usize {
+        match self {
+            Workers::Cpus => {
+                let cpus = num_cpus::get();
+                if cpus > 1 {
+                    cpus - 1
+                } else {
+                    cpus
+                }
+            }
+            Workers::Count(count) => count,
+            Workers::Auto => 1,
+        }
+    }
+}
+
+/// How a crack runs its workers, the same for every kind of target.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkerOptions {
+    workers: Workers,
+    pinned_cpus: Vec<usize>,
+    low_priority: bool,
+}
+
+impl WorkerOptions {
+    /// One worker per CPU but one, neither pinned nor at low priority.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the default of one worker per CPU but one.
+    /// A crack refuses to start with `Workers::Count(0)`.
+    pub fn with_workers(mut self, workers: Workers) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Pins worker `i` to CPU `cpus[i % cpus.len()]`, only on Linux.
+    pub fn with_pinned_cpus(mut self, cpus: &[usize]) -> Self {
+        self.pinned_cpus = cpus.to_vec();
+        self
+    }
+
+    /// Runs the workers & the processes they spawn at the lowest priority,
+    /// so that a crack in the background doesn't slow down the machine.
+    pub fn with_low_priority(mut self) -> Self {
+        self.low_priority = true;
+        self
+    }
+
+    pub fn workers(&self) -> Workers {
+        self.workers
+    }
+
+    /// Empty if the workers aren't pinned.
+    pub fn pinned_cpus(&self) -> &[usize] {
+        &self.pinned_cpus
+    }
+
+    pub fn low_priority(&self) -> bool {
+        self.low_priority
+    }
+
+    /// The CPU worker `tid` is pinned to, if any.
+    pub(crate) fn cpu(&self, tid: usize) -> Option<usize> {
+        let cpus = &self.pinned_cpus;
+        (!cpus.is_empty()).then(|| cpus[tid % cpus.len()])
+    }
+}
+
+/// How long every worker count of the benchmark checks candidates.
+const BENCHMARK_DURATION: Duration = Duration::from_millis(200);
+
+/// Up to this many workers per CPU are benchmarked.
+const MAX_WORKERS_PER_CPU: usize = 4;
+
+/// The nice value of low priority workers, the lowest priority there is.
+const LOW_PRIORITY: i32 = 19;
+
+/// Checks `candidate` over & over with 1, 2, 4, ... workers, only the time counts.
+/// Stops doubling once that gains less than 10% more candidates per second,
+/// or right away once `cancelled` is true, with the best count so far.
+pub(crate) fn benchmark<O: Oracle>(
+    config: &O::Config,
+    candidate: &str,
+    setup: impl Fn(usize) + Sync,
+    cancelled: impl Fn() -> bool + Sync,
+) -> usize {
+    let max = MAX_WORKERS_PER_CPU * num_cpus::get();
+    let run = |count| throughput::<O>(config, candidate, count, &setup, &cancelled);
+    let (mut best, mut best_rate) = (1, run(1));
+    let mut count = 2;
+    while count <= max && !cancelled() {
+        let rate = run(count);
+        if rate < best_rate * 1.1 || cancelled() {
+            break;
+        }
+        (best, best_rate) = (count, rate);
+        count *= 2;
+    }
+    info!(
+        "Benchmark picked {} workers at {:.0} candidates/s",
+        best, best_rate
+    );
+    best
+}
+
+/// Candidates per second of all workers together, not counting the creation of the oracles.
+fn throughput<O: Oracle>(
+    config: &O::Config,
+    candidate: &str,
+    workers: usize,
+    setup: &(impl Fn(usize) + Sync),
+    cancelled: &(impl Fn() -> bool + Sync),
+) -> f64 {
+    let checks: u64 = thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|tid| {
+                scope.spawn(move || {
+                    setup(tid);
+                    let mut oracle = O::create(config, tid);
+                    let start = Instant::now();
+                    let mut checks = 0;
+                    while start.elapsed() < BENCHMARK_DURATION && !cancelled() {
+                        oracle.check(candidate);
+                        checks += 1;
+                    }
+                    oracle.close();
+                    checks
+                })
+            })
+            .collect::<Vec<_>>();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    });
+    checks as f64 / BENCHMARK_DURATION.as_secs_f64()
+}
+
+/// Pins the calling worker thread to `cpu` & lowers its priority, if asked to.
+/// Both carry over to the processes the worker spawns. Failures are only logged,
+/// the worker still does its share.
+pub(crate) fn setup(tid: usize, cpu: Option<usize>, low_priority: bool) {
+    if let Some(cpu) = cpu {
+        if let Err(e) = pin(cpu) {
+            warn!("Thread {:>2} couldn't be pinned to CPU {}: {}", tid, cpu, e);
+        }
+    }
+    if low_priority {
+        if let Err(e) = lower_priority() {
+            warn!("Thread {:>2} couldn't lower its priority: {}", tid, e);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "there's no such CPU",
+        ));
+    }
+    // SAFETY: the set is plain data & the CPU is in its bounds
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        // 0 is the calling thread
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin(_cpu: usize) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU pinning is only supported on Linux",
+    ))
+}
+
+/// On Linux only the calling thread, elsewhere the whole process.
+#[cfg(unix)]
+fn lower_priority() -> io::Result<()> {
+    // SAFETY: no pointers involved
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn lower_priority() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "low priority is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests_workers {
+    use super::*;
+    use crate::oracle::Verdict;
+
+    /// Waits like a target that's busy with IO.
+    struct Sleepy;
+
+    impl Oracle for Sleepy {
+        type Config = ();
+
+        fn create(_config: &(), _tid: usize) -> Self {
+            Sleepy
+        }
+
+        fn check(&mut self, _candidate: &str) -> Verdict {
+            thread::sleep(Duration::from_millis(2));
+            Verdict::Incorrect
+        }
+
+        fn close(self) {}
+    }
+
+    #[test]
+    fn test_benchmark_oversubscribes_waiting_oracles() {
+        let workers = benchmark::<Sleepy>(&(), "", |_| {}, || false);
+        assert!(workers > num_cpus::get(), "{} workers", workers);
+    }
+
+    #[test]
+    fn test_benchmark_stops_when_cancelled() {
+        let start = Instant::now();
+        assert_eq!(benchmark::<Sleepy>(&(), "", |_| {}, || true), 1);
+        assert!(start.elapsed() < BENCHMARK_DURATION);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_setup() {
+        thread::spawn(|| {
+            setup(0, Some(0), true);
+            // SAFETY: plain data & calls about the calling thread
+            unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+                assert!(libc::CPU_ISSET(0, &set));
+                assert_eq!(libc::CPU_COUNT(&set), 1);
+                assert_eq!(libc::getpriority(libc::PRIO_PROCESS, 0), LOW_PRIORITY);
+            }
+        })
+        .join()
+        .unwrap();
+    }
+}
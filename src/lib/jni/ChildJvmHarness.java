@@ -0,0 +1,193 @@
+import java.io.BufferedReader;
+import java.io.ByteArrayInputStream;
+import java.io.ByteArrayOutputStream;
+import java.io.FileDescriptor;
+import java.io.FileInputStream;
+import java.io.FileOutputStream;
+import java.io.InputStreamReader;
+import java.io.PrintStream;
+import java.lang.reflect.Constructor;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+import java.nio.charset.StandardCharsets;
+import java.util.Arrays;
+
+/**
+ * Runs a target class in a child JVM for tokio_pw's ChildJvm oracle.
+ *
+ * <pre>
+ * java -cp harness:classpath ChildJvmHarness wrapper class write read close
+ * java -cp harness:classpath ChildJvmHarness main class args...
+ * </pre>
+ *
+ * Reads one candidate per line from stdin & answers every one with one line on stdout,
+ * fields are separated by tabs & escaped:
+ *
+ * <pre>
+ * ready                            class & methods found, sent once before the first candidate
+ * missing-class                    the class isn't on the classpath
+ * missing-method NAME              the class lacks the method
+ * failed MESSAGE                   the static initializer or constructor threw
+ * ok EXIT STDOUT STDERR            the response, EXIT is - in wrapper mode
+ * error MESSAGE                    a wrapper method threw
+ * exit STDOUT STDERR               the target called System.exit, the process ends next
+ * </pre>
+ *
+ * The target's System.out & System.err are captured, so that they can't get mixed into the answers.
+ */
+public class ChildJvmHarness {
+    private static final PrintStream ANSWERS =
+            new PrintStream(new FileOutputStream(FileDescriptor.out), true, StandardCharsets.UTF_8);
+
+    private static ByteArrayOutputStream out = new ByteArrayOutputStream();
+    private static ByteArrayOutputStream err = new ByteArrayOutputStream();
+    /** Set while the target works on a candidate. */
+    private static volatile boolean running;
+
+    public static void main(String[] args) throws Exception {
+        BufferedReader candidates = new BufferedReader(new InputStreamReader(
+                new FileInputStream(FileDescriptor.in), StandardCharsets.UTF_8));
+        Runtime.getRuntime().addShutdownHook(new Thread(ChildJvmHarness::exiting));
+
+        Class<?> target;
+        try {
+            target = Class.forName(args[1]);
+        } catch (ExceptionInInitializerError e) {
+            answer("failed", e.getCause().toString());
+            return;
+        } catch (ClassNotFoundException | LinkageError e) {
+            answer("missing-class");
+            return;
+        }
+        if (args[0].equals("main")) {
+            Method main = find(target, "main", String[].class);
+            if (main == null) {
+                return;
+            }
+            if (!Modifier.isStatic(main.getModifiers())) {
+                answer("missing-method", "main");
+                return;
+            }
+            runMain(candidates, main, Arrays.copyOfRange(args, 2, args.length));
+        } else {
+            Method write = find(target, args[2], byte[].class, int.class, int.class);
+            Method read = find(target, args[3]);
+            Method close = find(target, args[4]);
+            if (write == null || read == null || close == null) {
+                return;
+            }
+            Object instance;
+            try {
+                Constructor<?> constructor = target.getDeclaredConstructor();
+                constructor.setAccessible(true);
+                instance = constructor.newInstance();
+            } catch (InvocationTargetException e) {
+                answer("failed", e.getCause().toString());
+                return;
+            } catch (NoSuchMethodException e) {
+                answer("missing-method", "<init>");
+                return;
+            }
+            runWrapper(candidates, instance, write, read, close);
+        }
+    }
+
+    private static void runMain(BufferedReader candidates, Method main, String[] args)
+            throws Exception {
+        answer("ready");
+        String candidate;
+        while ((candidate = candidates.readLine()) != null) {
+            System.setIn(new ByteArrayInputStream(
+                    (candidate + "\n").getBytes(StandardCharsets.UTF_8)));
+            capture();
+            String exit = "0";
+            try {
+                main.invoke(null, (Object) args.clone());
+            } catch (InvocationTargetException e) {
+                // an uncaught exception ends main, like it would end a java process
+                e.getCause().printStackTrace();
+                exit = "1";
+            }
+            answerCaptured("ok", exit);
+        }
+    }
+
+    private static void runWrapper(BufferedReader candidates, Object instance, Method write,
+            Method read, Method close) throws Exception {
+        answer("ready");
+        String candidate;
+        while ((candidate = candidates.readLine()) != null) {
+            byte[] bytes = (candidate + "\n").getBytes(StandardCharsets.UTF_8);
+            capture();
+            try {
+                write.invoke(instance, bytes, 0, bytes.length);
+                Object response = read.invoke(instance);
+                running = false;
+                answer("ok", "-", String.valueOf(response), "");
+            } catch (InvocationTargetException e) {
+                running = false;
+                answer("error", e.getCause().toString());
+            }
+        }
+        close.invoke(instance);
+    }
+
+    /** Looks the method up like JNI does, whatever its visibility & wherever it's declared. */
+    private static Method find(Class<?> target, String name, Class<?>... parameters) {
+        for (Class<?> c = target; c != null; c = c.getSuperclass()) {
+            try {
+                Method method = c.getDeclaredMethod(name, parameters);
+                method.setAccessible(true);
+                return method;
+            } catch (NoSuchMethodException e) {
+                // declared further up, if at all
+            }
+        }
+        answer("missing-method", name);
+        return null;
+    }
+
+    private static void capture() {
+        out = new ByteArrayOutputStream();
+        err = new ByteArrayOutputStream();
+        System.setOut(new PrintStream(out, true, StandardCharsets.UTF_8));
+        System.setErr(new PrintStream(err, true, StandardCharsets.UTF_8));
+        running = true;
+    }
+
+    private static void answerCaptured(String kind, String... fields) {
+        running = false;
+        System.out.flush();
+        System.err.flush();
+        String[] all = Arrays.copyOf(fields, fields.length + 2);
+        all[fields.length] = out.toString(StandardCharsets.UTF_8);
+        all[fields.length + 1] = err.toString(StandardCharsets.UTF_8);
+        answer(kind, all);
+    }
+
+    /** Shutdown hook, answers the candidate the target exited on. */
+    private static void exiting() {
+        if (running) {
+            answerCaptured("exit");
+        }
+    }
+
+    private static synchronized void answer(String kind, String... fields) {
+        StringBuilder line = new StringBuilder(kind);
+        for (String field : fields) {
+            line.append('\t');
+            for (char c : field.toCharArray()) {
+                switch (c) {
+                    case '\\': line.append("\\\\"); break;
+                    case '\t': line.append("\\t"); break;
+                    case '\n': line.append("\\n"); break;
+                    case '\r': line.append("\\r"); break;
+                    default: line.append(c);
+                }
+            }
+        }
+        ANSWERS.print(line.append('\n'));
+        ANSWERS.flush();
+    }
+}
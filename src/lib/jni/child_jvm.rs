@@ -0,0 +1,495 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    env,
+    ffi::OsStr,
+    fs,
+    hash::{Hash, Hasher},
+    io::Write,
+    iter,
+    path::{Path, PathBuf},
+    process::{self, Child, ChildStdin, Command, Stdio},
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+
+use crate::child::spawn::read_lines;
+use crate::jni::target::{JniMode, JniTarget, TargetError, MAIN_SIG};
+use crate::oracle::{Oracle, Response, SuccessCriterion, Verdict};
+
+/// Drives the target class in the child, its docs describe the line protocol.
+const HARNESS: &str = include_str!("ChildJvmHarness.java");
+const HARNESS_CLASS: &str = "ChildJvmHarness";
+
+/// Harness class directories by `java` executable, compiled once per process.
+static HARNESSES: Mutex<Vec<(String, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Written after every candidate, the harness reads line by line.
+const TERMINATOR: char = '\n';
+
+/// How often a child that's about to exit is checked on.
+const REAP_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Everything the `ChildJvm` oracle needs from the crack parameters.
+#[derive(Debug, Clone)]
+pub struct ChildJvmConfig {
+    pub target: JniTarget,
+    pub criterion: SuccessCriterion,
+}
+
+/// Oracle that runs the target class in its own `java` process per worker,
+/// so that a misbehaving class can't take down the other workers or the cracker.
+///
+/// The child runs a small harness class, which loads the target class & calls its write,
+/// read & close methods or its `main`, depending on the target's `JniMode`. The target's
+/// `System.out` & `System.err` can't get in the way of the answers, so prompts & log lines
+/// don't matter. In `JniMode::Main` the response carries both & the exit code, like with
+/// `MainRunner`; in `JniMode::Wrapper` it's the string read returns.
+///
+/// A target that calls `System.exit` or `Runtime.halt` ends its child, the exit code is the
+/// response to the candidate & the next candidate gets a fresh child. One that doesn't answer
+/// within the target's timeout is restarted & the candidate reported as an oracle error.
+///
+/// The harness gets compiled with the `java -m jdk.compiler` of the target on first use,
+/// which needs a JDK 11 or newer.
+pub struct ChildJvm {
+    target: JniTarget,
+    criterion: SuccessCriterion,
+    // None until the first candidate & after the target exited
+    harness: Option<Harness>,
+    // reused for every candidate, so that the loop doesn't allocate
+    input: String,
+}
+
+/// A child running the harness.
+struct Harness {
+    child: Child,
+    stdin: ChildStdin,
+    // lines from the reader thread, so that waiting for an answer can time out
+    lines: Receiver<String>,
+}
+
+/// Compiles the harness for `java` into a directory that's kept for later runs,
+/// named after the harness source, so that every version gets its own.
+fn harness_classes(java: &str) -> Result<PathBuf, TargetError> {
+    let mut harnesses = HARNESSES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, dir)) = harnesses
+        .iter()
+        .find(|(compiled_for, _)| compiled_for == java)
+    {
+        return Ok(dir.clone());
+    }
+
+    let mut hasher = DefaultHasher::new();
+    (HARNESS, java).hash(&mut hasher);
+    let dir = env::temp_dir().join(format!("tokio_pw_harness_{:x}", hasher.finish()));
+    if !dir.join(format!("{}.class", HARNESS_CLASS)).exists() {
+        // compiled next to it & moved in one go, other processes may compile it too
+        let compiling = dir.with_extension(process::id().to_string());
+        compile_harness(java, &compiling)?;
+        if fs::rename(&compiling, &dir).is_err() {
+            let _ = fs::remove_dir_all(&compiling);
+        }
+    }
+    harnesses.push((java.into(), dir.clone()));
+    Ok(dir)
+}
+
+fn compile_harness(java: &str, dir: &Path) -> Result<(), TargetError> {
+    fs::create_dir_all(dir)?;
+    let source = dir.join(format!("{}.java", HARNESS_CLASS));
+    fs::write(&source, HARNESS)?;
+    let output = Command::new(java)
+        .args(["-m", "jdk.compiler/com.sun.tools.javac.Main", "-d"])
+        .arg(dir)
+        .arg(&source)
+        .output()?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(dir);
+        return Err(TargetError::ChildFailed(format!(
+            "compiling the harness failed, is {} a JDK? {}",
+            java,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Makes sure the child can start & finds the target's class & methods,
+/// before any worker starts. Compiles the harness, if it isn't yet.
+pub fn check_target(target: &JniTarget) -> Result<(), TargetError> {
+    if target.mode() == JniMode::Wrapper {
+        target.check_signatures()?;
+    }
+    Harness::spawn(target)?.close(target.timeout());
+    Ok(())
+}
+
+impl Harness {
+    /// Starts `java` with the harness & waits until it found the target's class & methods.
+    fn spawn(target: &JniTarget) -> Result<Self, TargetError> {
+        let harness = harness_classes(target.java())?;
+        // rejects entries with separators
+        target.classpath_string()?;
+        let classpath = env::join_paths(
+            iter::once(harness.as_os_str()).chain(target.classpath().iter().map(OsStr::new)),
+        )
+        .map_err(|_| TargetError::InvalidClasspath(harness.to_string_lossy().into_owned()))?;
+
+        let mut command = Command::new(target.java());
+        command
+            .args(target.jvm_options())
+            .arg("-cp")
+            .arg(classpath)
+            .arg(HARNESS_CLASS);
+        // the harness wants the binary name, e.g. com.example.Login
+        let class = target.class_name().replace('/', ".");
+        match target.mode() {
+            JniMode::Main => command.arg("main").arg(class).args(target.main_args()),
+            JniMode::Wrapper => command.arg("wrapper").arg(class).args([
+                target.write().name(),
+                target.read().name(),
+                target.close().name(),
+            ]),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let lines = read_lines(child.stdout.take().unwrap());
+        let harness = Self {
+            child,
+            stdin,
+            lines,
+        };
+
+        let ready = harness.lines.recv_timeout(target.timeout());
+        let fields = ready.as_deref().map(fields).unwrap_or_default();
+        let error = match fields.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+            ["ready"] => return Ok(harness),
+            ["missing-class"] => TargetError::MissingClass {
+                class: target.class_name().into(),
+                classpath: target.classpath().to_vec(),
+            },
+            ["missing-method", name] => TargetError::MissingMethod {
+                class: target.class_name().into(),
+                name: name.into(),
+                sig: sig_of(target, name).into(),
+            },
+            ["failed", message] => TargetError::ChildFailed(message.into()),
+            _ => TargetError::ChildFailed(match ready {
+                Ok(line) => format!("unexpected answer {:?}", line),
+                Err(RecvTimeoutError::Timeout) => {
+                    format!("no answer within {:?}", target.timeout())
+                }
+                Err(RecvTimeoutError::Disconnected) => "it exited right away".into(),
+            }),
+        };
+        harness.close(target.timeout());
+        Err(error)
+    }
+
+    /// Sends the candidate line & waits for the answer.
+    fn exchange(&mut self, input: &str, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        if self.stdin.write_all(input.as_bytes()).is_err() || self.stdin.flush().is_err() {
+            return Err(RecvTimeoutError::Disconnected);
+        }
+        self.lines.recv_timeout(timeout)
+    }
+
+    /// Closing stdin lets the harness call the target's close method & exit.
+    fn close(self, timeout: Duration) {
+        let Harness {
+            mut child, stdin, ..
+        } = self;
+        drop(stdin);
+        reap(&mut child, timeout);
+    }
+}
+
+/// Waits up to `timeout` for the child to exit, kills it otherwise.
+/// Returns the exit code, None if it got killed by a signal.
+fn reap(child: &mut Child, timeout: Duration) -> Option<i32> {
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.code(),
+            Ok(None) if start.elapsed() < timeout => thread::sleep(REAP_INTERVAL),
+            _ => {
+                let _ = child.kill();
+                return child.wait().ok().and_then(|status| status.code());
+            }
+        }
+    }
+}
+
+/// Splits an answer of the harness into its kind & fields & undoes the escaping.
+fn fields(line: &str) -> Vec<String> {
+    line.strip_suffix('\n')
+        .unwrap_or(line)
+        .split('\t')
+        .map(|field| {
+            let mut unescaped = String::with_capacity(field.len());
+            let mut chars = field.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    unescaped.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('t') => unescaped.push('\t'),
+                    Some('n') => unescaped.push('\n'),
+                    Some('r') => unescaped.push('\r'),
+                    Some(c) => unescaped.push(c),
+                    None => {}
+                }
+            }
+            unescaped
+        })
+        .collect()
+}
+
+/// The signature of the method the harness didn't find.
+fn sig_of<'a>(target: &'a JniTarget, name: &str) -> &'a str {
+    match target.mode() {
+        JniMode::Main => MAIN_SIG,
+        JniMode::Wrapper if name == "<init>" => target.constructor_sig(),
+        JniMode::Wrapper if name == target.write().name() => target.write().sig(),
+        JniMode::Wrapper if name == target.read().name() => target.read().sig(),
+        JniMode::Wrapper => target.close().sig(),
+    }
+}
+
+impl ChildJvm {
+    /// The running child, a fresh one if there's none or the target exited in between,
+    /// e.g. from a thread of its own.
+    fn running<'a>(
+        harness: &'a mut Option<Harness>,
+        target: &JniTarget,
+    ) -> Result<&'a mut Harness, TargetError> {
+        if let Some(running) = harness {
+            if let Ok(Some(status)) = running.child.try_wait() {
+                warn!(
+                    "Java child exited between candidates ({}), restarting it.",
+                    status
+                );
+                *harness = None;
+            }
+        }
+        if harness.is_none() {
+            *harness = Some(Harness::spawn(target)?);
+        }
+        Ok(harness.as_mut().unwrap())
+    }
+
+    /// The response to a candidate the target exited on, the child is gone afterwards.
+    fn exited(&mut self, stdout: String, stderr: String) -> Verdict {
+        let mut harness = self.harness.take().unwrap();
+        let Some(exit_code) = reap(&mut harness.child, self.target.timeout()) else {
+            return Verdict::Error("java child got killed".into());
+        };
+        let response = match self.target.mode() {
+            JniMode::Main => Response {
+                stdout,
+                stderr,
+                exit_code: Some(exit_code),
+                truncated: false,
+            },
+            // like in this process, an exit ends the call without a response
+            JniMode::Wrapper => Response {
+                exit_code: Some(exit_code),
+                ..Response::default()
+            },
+        };
+        self.criterion.judge(&response)
+    }
+}
+
+impl Oracle for ChildJvm {
+    type Config = ChildJvmConfig;
+
+    /// The child starts with the first candidate, `check_target` made sure it can.
+    fn create(config: &Self::Config, _tid: usize) -> Self {
+        Self {
+            target: config.target.clone(),
+            criterion: config.criterion.clone(),
+            harness: None,
+            input: String::new(),
+        }
+    }
+
+    fn check(&mut self, candidate: &str) -> Verdict {
+        self.input.clear();
+        self.input.push_str(candidate);
+        self.input.push(TERMINATOR);
+
+        let timeout = self.target.timeout();
+        let harness = match Self::running(&mut self.harness, &self.target) {
+            Ok(harness) => harness,
+            Err(e) => return Verdict::Error(format!("java child didn't start: {}", e)),
+        };
+        let answer = match harness.exchange(&self.input, timeout) {
+            Ok(answer) => answer,
+            // Runtime.halt or a crash, the exit code is all there is
+            Err(RecvTimeoutError::Disconnected) => {
+                return self.exited(String::new(), String::new())
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                warn!(
+                    "Java child didn't answer \"{}\" within {:?}, restarting it.",
+                    candidate, timeout
+                );
+                let _ = harness.child.kill();
+                self.harness.take().unwrap().close(timeout);
+                return Verdict::Error(format!("no answer within {:?}", timeout));
+            }
+        };
+
+        let mut fields = fields(&answer).into_iter();
+        match (
+            fields.next().as_deref(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) {
+            (Some("ok"), Some(exit_code), Some(stdout), Some(stderr)) => {
+                let response = Response {
+                    stdout,
+                    stderr,
+                    // "-" in wrapper mode
+                    exit_code: exit_code.parse().ok(),
+                    // the child stopped writing before it finished the line
+                    truncated: !answer.ends_with('\n'),
+                };
+                self.criterion.judge(&response)
+            }
+            (Some("exit"), Some(stdout), Some(stderr), None) => self.exited(stdout, stderr),
+            (Some("error"), Some(message), None, None) => Verdict::Error(message),
+            _ => Verdict::Error(format!("unexpected answer {:?}", answer)),
+        }
+    }
+
+    fn close(mut self) {
+        if let Some(harness) = self.harness.take() {
+            harness.close(self.target.timeout());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_child_jvm {
+    use super::*;
+    use crate::engine::{WorkerOptions, Workers};
+    use crate::jni::{crack, tests_jni::compile, CrackParam, JniIsolation};
+    use crate::oracle::Matcher;
+
+    /// Prompts on stdout, which mustn't be taken for the answer, & only accepts "ba".
+    const LOGIN: &str = r#"
+        public class ChildLogin {
+            private String line;
+            public void writePipe(byte[] bytes, int offset, int length) {
+                System.out.println("Password:");
+                line = new String(bytes, offset, length).trim();
+            }
+            public String spawn() {
+                return line.equals("ba") ? "correct" : "wrong";
+            }
+            public void closePipe() {}
+        }"#;
+
+    /// Exits with 0 for "ba" & with 3 otherwise, after a prompt.
+    const EXIT: &str = r#"
+        public class ChildExit {
+            public static void main(String[] args) throws Exception {
+                System.out.print("Password: ");
+                String line = new java.io.BufferedReader(
+                    new java.io.InputStreamReader(System.in)).readLine();
+                System.out.println(line.equals("ba") ? "welcome" : "wrong");
+                System.exit(line.equals("ba") ? 0 : 3);
+            }
+        }"#;
+
+    #[test]
+    fn test_crack_wrapper() {
+        let Some(classpath) = compile("ChildLogin", LOGIN) else {
+            return;
+        };
+        let target = JniTarget::new()
+            .with_classpath(&classpath)
+            .with_class("ChildLogin", "()V")
+            .with_isolation(JniIsolation::ChildJvm);
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "correct")
+            .with_target(target)
+            .with_worker_options(WorkerOptions::new().with_workers(Workers::Count(2)));
+        let res = crack(param).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.oracle_errors(), 0);
+    }
+
+    #[test]
+    fn test_main_exits() {
+        let Some(classpath) = compile("ChildExit", EXIT) else {
+            return;
+        };
+        let target = JniTarget::new()
+            .with_classpath(&classpath)
+            .with_main_class("ChildExit")
+            .with_isolation(JniIsolation::ChildJvm);
+        check_target(&target).unwrap();
+
+        let config = ChildJvmConfig {
+            target: target.clone(),
+            criterion: SuccessCriterion::ExitCode(3).and(SuccessCriterion::StdoutMatches(
+                Matcher::literal("Password: wrong"),
+            )),
+        };
+        let mut oracle = ChildJvm::create(&config, 0);
+        // every exit ends the child, the next candidate gets a fresh one
+        assert_eq!(oracle.check("aa"), Verdict::Correct);
+        assert_eq!(oracle.check("ab"), Verdict::Correct);
+        assert_eq!(oracle.check("ba"), Verdict::Incorrect);
+        oracle.close();
+
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "welcome").with_target(target);
+        let res = crack(param).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.oracle_errors(), 0);
+    }
+
+    #[test]
+    fn test_check_target() {
+        let Some(classpath) = compile("ChildLogin", LOGIN) else {
+            return;
+        };
+        let target = JniTarget::new()
+            .with_classpath(&classpath)
+            .with_isolation(JniIsolation::ChildJvm);
+        assert!(matches!(
+            check_target(&target.clone().with_class("NoSuchClass", "()V")),
+            Err(TargetError::MissingClass { .. })
+        ));
+        assert!(matches!(
+            check_target(
+                &target
+                    .with_class("ChildLogin", "()V")
+                    .with_read("answer", "()Ljava/lang/String;")
+            ),
+            Err(TargetError::MissingMethod { name, .. }) if name == "answer"
+        ));
+    }
+
+    #[test]
+    fn test_fields() {
+        assert_eq!(
+            fields("ok\t0\ta\\tb\\\\n\\nc\t\n"),
+            ["ok", "0", "a\tb\\n\nc", ""]
+        );
+    }
+}
@@ -1,35 +1,161 @@
-use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
-use std::time::Instant;
-
+use crate::engine::{self, CrackError, CrackHandle, CrackResult};
 use crate::jni::{
-    internal::InternalCrackData, param_interface::CrackParam, result::CrackResult, tasks::tasks,
+    child_jvm::{self, ChildJvm, ChildJvmConfig},
+    main_runner::{MainRunner, MainRunnerConfig},
+    param_interface::CrackParam,
+    runtime,
+    target::{JniIsolation, JniMode},
+    wrapper::{PasswordWrapper, PasswordWrapperConfig},
 };
 
-pub fn crack(param: CrackParam) -> CrackResult {
-    let param = InternalCrackData::from(param);
-    let param = Arc::from(param);
-
-    // shared atomic bool so that all threads can look if one already found a solution
-    // so they can stop their work. This only gets checked at every millionth iteration
-    // for better performance.
-    let done = Arc::from(AtomicBool::from(false));
-    let instant = Instant::now();
-    let handles = tasks(param.clone(), done);
-
-    // wait for all threads
-    let solution = handles
-        .into_iter()
-        .flat_map(|h| h.join().unwrap()) // result of the Option<String> from the threads
-        .last(); // extract from the collection
-
-    let seconds = instant.elapsed().as_secs_f64();
-
-    let param =
-        Arc::try_unwrap(param).unwrap_or_else(|_| panic!("There should only be one reference!"));
-    if let Some(solution) = solution {
-        CrackResult::new_success(param, seconds, solution)
-    } else {
-        CrackResult::new_failure(param, seconds)
+/// Cracks the password through the java class configured in the param's target,
+/// either a wrapper class or a class's `main`, in this process or in child JVMs.
+/// Fails before the first candidate if the class or one of its methods is missing,
+/// if the criterion looks at stderr in `JniMode::Wrapper`,
+/// if the attack's keyspace overflows, see `Attack::keyspace`, if its wordlist
+/// can't be read, or for `Workers::Count(0)`.
+pub fn crack(param: CrackParam) -> Result<CrackResult, CrackError> {
+    Ok(start(param)?.wait())
+}
+
+/// Like `crack`, but returns right away, to cancel, pause or watch the workers.
+pub fn start(param: CrackParam) -> Result<CrackHandle, CrackError> {
+    let target = param.target().clone();
+    let criterion = param.criterion().clone();
+    let search = param.search().clone();
+    // read returns a string only, an exit ends the call with its code
+    if target.mode() == JniMode::Wrapper && criterion.reads_stderr() {
+        return Err(CrackError::Criterion("stderr"));
+    }
+    if target.isolation() == JniIsolation::ChildJvm {
+        child_jvm::check_target(&target)?;
+        return engine::start::<ChildJvm>(search, ChildJvmConfig { target, criterion });
+    }
+
+    runtime::check_target(&target)?;
+    match target.mode() {
+        JniMode::Wrapper => {
+            engine::start::<PasswordWrapper>(search, PasswordWrapperConfig { target, criterion })
+        }
+        JniMode::Main => {
+            engine::start::<MainRunner>(search, MainRunnerConfig { target, criterion })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_crack {
+    use super::*;
+    use crate::engine::{WorkerOptions, Workers};
+    use crate::jni::{tests_jni::compile, JniTarget, TargetError};
+    use crate::oracle::SuccessCriterion;
+
+    /// Only accepts "ba".
+    const LOGIN: &str = r#"
+        public class CrackLogin {
+            private String line;
+            public void writePipe(byte[] bytes, int offset, int length) {
+                line = new String(bytes, offset, length).trim();
+            }
+            public String spawn() {
+                return line.equals("ba") ? "correct" : "wrong";
+            }
+            public void closePipe() {}
+        }"#;
+
+    #[test]
+    fn test_crack_wrapper() {
+        let Some(classpath) = compile("CrackLogin", LOGIN) else {
+            return;
+        };
+        let target = JniTarget::new()
+            .with_classpath(&classpath)
+            .with_class("CrackLogin", "()V");
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "correct").with_target(target);
+        let res = crack(param).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+    }
+
+    /// Throws for "a", returns null for "b" & only accepts "ba".
+    const FAULTY: &str = r#"
+        public class CrackFaulty {
+            private String line;
+            public void writePipe(byte[] bytes, int offset, int length) {
+                line = new String(bytes, offset, length).trim();
+                if (line.equals("a")) {
+                    throw new IllegalStateException("broken");
+                }
+            }
+            public String spawn() {
+                if (line.equals("b")) {
+                    return null;
+                }
+                return line.equals("ba") ? "correct" : "wrong";
+            }
+            public void closePipe() {
+                throw new IllegalStateException("broken");
+            }
+        }"#;
+
+    #[test]
+    fn test_crack_wrapper_errors() {
+        let Some(classpath) = compile("CrackFaulty", FAULTY) else {
+            return;
+        };
+        let target = JniTarget::new()
+            .with_classpath(&classpath)
+            .with_class("CrackFaulty", "()V");
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "correct")
+            .with_target(target)
+            .with_worker_options(WorkerOptions::new().with_workers(Workers::Count(1)));
+        // the workers go on after the errors & the failing close
+        let res = crack(param).unwrap();
+        assert_eq!(res.solution().as_deref(), Some("ba"));
+        assert_eq!(res.oracle_errors(), 2);
+    }
+
+    #[test]
+    fn test_crack_invalid_target() {
+        let Some(classpath) = compile("CrackLogin", LOGIN) else {
+            return;
+        };
+        let target = JniTarget::new().with_classpath(&classpath);
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "correct");
+
+        let missing = target.clone().with_class("NoSuchClass", "()V");
+        assert!(matches!(
+            crack(param.clone().with_target(missing)),
+            Err(CrackError::Target(TargetError::MissingClass { .. }))
+        ));
+        let wrong_write = target
+            .with_class("CrackLogin", "()V")
+            .with_write("writePipe", "(Ljava/lang/String;)V");
+        assert!(matches!(
+            start(param.clone().with_target(wrong_write.clone())),
+            Err(CrackError::Target(TargetError::InvalidSignature { .. }))
+        ));
+        let isolated = wrong_write.with_isolation(JniIsolation::ChildJvm);
+        assert!(matches!(
+            start(param.with_target(isolated)),
+            Err(CrackError::Target(TargetError::InvalidSignature { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_wrapper_rejects_stderr_criteria() {
+        let param = CrackParam::new(Box::from(['a', 'b']), 0, 2, "correct");
+        for isolation in [JniIsolation::InProcess, JniIsolation::ChildJvm] {
+            let target = JniTarget::new().with_isolation(isolation);
+            for criterion in [
+                SuccessCriterion::StderrEmpty,
+                SuccessCriterion::StderrNotEmpty.not(),
+            ] {
+                let param = param
+                    .clone()
+                    .with_target(target.clone())
+                    .with_criterion(criterion);
+                assert!(matches!(start(param), Err(CrackError::Criterion("stderr"))));
+            }
+        }
     }
 }
@@ -47,6 +47,50 @@ pub fn indices_to_string(buf: &mut String, alphabet: &[char], indices: &[isize])
         .for_each(|char| buf.push(char))
 }
 
+/// The indices after `index` increments of `indices_create(max_length, min_length)`,
+/// without stepping through all of them. None if `index` is past the last combination.
+pub fn indices_at(
+    alphabet: &[char],
+    max_length: u8,
+    min_length: u8,
+    mut index: u128,
+) -> Option<Box<[isize]>> {
+    let mut indices = indices_create(max_length, min_length);
+    let len = alphabet.len() as u128;
+    for length in min_length..=max_length {
+        match len.checked_pow(length.into()) {
+            // not this length, skip all of its combinations
+            Some(count) if index >= count => index -= count,
+            _ => {
+                let first = (max_length - length) as usize;
+                for slot in indices[first..].iter_mut().rev() {
+                    *slot = (index % len) as isize;
+                    index /= len;
+                }
+                return Some(indices);
+            }
+        }
+    }
+    None
+}
+
+/// The inverse of [`indices_at`]: the number of increments from
+/// `indices_create(_, min_length)` to the indices. None if that doesn't fit into a `u128`.
+pub fn indices_to_index(alphabet: &[char], min_length: u8, indices: &[isize]) -> Option<u128> {
+    let len = alphabet.len() as u128;
+    let length = indices.iter().filter(|index| **index != -1).count() as u32;
+    // all combinations of the shorter lengths come first
+    let mut index: u128 = 0;
+    for shorter in u32::from(min_length)..length {
+        index = index.checked_add(len.checked_pow(shorter)?)?;
+    }
+    let mut value: u128 = 0;
+    for slot in indices.iter().filter(|index| **index != -1) {
+        value = value.checked_mul(len)?.checked_add(*slot as u128)?;
+    }
+    index.checked_add(value)
+}
+
 /// Increments the indices array by a given number.
 /// Returns Ok() on next number or Err() on final
 /// overflow (=done).
@@ -54,7 +98,7 @@ pub fn indices_to_string(buf: &mut String, alphabet: &[char], indices: &[isize])
 pub fn indices_increment_by(
     alphabet: &[char],
     indices: &mut [isize],
-    add_value: usize,
+    add_value: u128,
 ) -> Result<(), &'static str> {
     if add_value == 0 {
         // Nothing to do
@@ -64,6 +108,7 @@ pub fn indices_increment_by(
     // The carry from the last iteration; in the first iteration the carry
     // is the add_value; in each further iteration its the actual carry
     let mut carry = add_value;
+    let len = alphabet.len() as u128;
 
     // we go from left to right => reverse
     for index in (0..indices.len()).rev() {
@@ -74,19 +119,23 @@ pub fn indices_increment_by(
 
         // the current index at this position in the indices array
         let current_value = indices[index];
-        // new value; possibly overflowed its range
-        let mut new_value = current_value + carry as isize;
+        // new value; possibly overflowed its range. -1 is one below 0
+        let mut new_value = if current_value < 0 {
+            carry - 1
+        } else {
+            (current_value as u128).checked_add(carry).ok_or(OVERFLOW)?
+        };
 
         // out of bounds? modulo!
-        if new_value >= alphabet.len() as isize {
+        if new_value >= len {
             // carry for next position/next iteration
-            carry = new_value as usize / alphabet.len();
-            new_value %= alphabet.len() as isize;
+            carry = new_value / len;
+            new_value %= len;
         } else {
             carry = 0;
         }
 
-        indices[index] = new_value;
+        indices[index] = new_value as isize;
     }
 
     if carry == 0 {
@@ -94,7 +143,40 @@ pub fn indices_increment_by(
     } else {
         // at the end its not the original state or the maximum value but some
         // invalid value
-        Err("Overflow detected! Data/state is now invalid and no longer reliable!")
+        Err(OVERFLOW)
+    }
+}
+
+const OVERFLOW: &str = "Overflow detected! Data/state is now invalid and no longer reliable!";
+
+/// Like [`indices_increment_by`], but every position has its own radix, e.g. the size
+/// of its charset. Digits start at 0, there are no empty slots.
+/// Returns Ok() on next number or Err() on final overflow (=done).
+#[inline]
+pub fn digits_increment_by(
+    radices: &[usize],
+    digits: &mut [usize],
+    add_value: u128,
+) -> Result<(), &'static str> {
+    let mut carry = add_value;
+
+    // we go from left to right => reverse
+    for index in (0..digits.len()).rev() {
+        if carry == 0 {
+            // done, no more carry to bring to the next position
+            break;
+        }
+
+        let radix = radices[index] as u128;
+        let new_value = (digits[index] as u128).checked_add(carry).ok_or(OVERFLOW)?;
+        carry = new_value / radix;
+        digits[index] = (new_value % radix) as usize;
+    }
+
+    if carry == 0 {
+        Ok(())
+    } else {
+        Err(OVERFLOW)
     }
 }
 
@@ -0,0 +1,255 @@
+use std::sync::Mutex;
+
+use jni::{
+    errors::Error,
+    objects::{GlobalRef, JClass, JObject, JStaticMethodID, JString, JThrowable, JValue},
+    signature::{Primitive, ReturnType},
+    Executor, JNIEnv,
+};
+
+use crate::jni::{
+    runtime,
+    target::{JniTarget, TargetError},
+};
+use crate::oracle::{Oracle, Response, SuccessCriterion, Verdict};
+
+/// `System.in`, `System.out` & `System.err` exist once per VM,
+/// so only one worker at a time may have them redirected.
+static STDIO: Mutex<()> = Mutex::new(());
+
+const SYSTEM: &str = "java/lang/System";
+const INPUT_STREAM: &str = "Ljava/io/InputStream;";
+const PRINT_STREAM: &str = "Ljava/io/PrintStream;";
+
+/// Everything the `MainRunner` oracle needs from the crack parameters.
+#[derive(Debug, Clone)]
+pub struct MainRunnerConfig {
+    pub target: JniTarget,
+    pub criterion: SuccessCriterion,
+}
+
+/// Oracle that runs an arbitrary class's `main` in the shared VM, no wrapper class needed.
+/// The candidate is fed through a replaced `System.in`, `System.out` & `System.err` are
+/// captured. An exception thrown out of `main` counts as exit code 1, like in a real `java` run,
+/// `System.exit` ends the call with its code instead of the process.
+/// If a call into the VM fails, e.g. describing the exception throws again,
+/// the candidate is an oracle error & the streams are put back all the same.
+///
+/// The redirection is process wide, so the calls of all workers are serialized.
+/// Static state of the target class survives between candidates.
+/// `create` panics if the target is invalid, `start` rules that out before any worker runs.
+pub struct MainRunner {
+    exec: Executor,
+    // an Option, so that the references can be released before the thread detaches
+    refs: Option<Refs>,
+    main: JStaticMethodID,
+    criterion: SuccessCriterion,
+    // reused for every candidate, so that appending the newline doesn't allocate
+    buf: String,
+}
+
+struct Refs {
+    class: GlobalRef,
+    args: GlobalRef,
+}
+
+impl Oracle for MainRunner {
+    type Config = MainRunnerConfig;
+
+    fn create(config: &Self::Config, _tid: usize) -> Self {
+        let target = &config.target;
+
+        // Attach the current thread to the shared JVM
+        let exec =
+            runtime::executor(target).unwrap_or_else(|e| panic!("Invalid java target: {}", e));
+
+        let (refs, main) = exec
+            .with_attached(|jni_env| {
+                let (class, main) = target.resolve_main(jni_env)?;
+
+                // String[] args, the same for every call
+                let args = jni_env.new_object_array(
+                    target.main_args().len() as i32,
+                    "java/lang/String",
+                    JObject::null(),
+                )?;
+                for (i, arg) in target.main_args().iter().enumerate() {
+                    let arg = jni_env.new_string(arg)?;
+                    jni_env.set_object_array_element(&args, i as i32, arg)?;
+                }
+
+                let refs = Refs {
+                    class: jni_env.new_global_ref(class)?,
+                    args: jni_env.new_global_ref(args)?,
+                };
+                Ok((refs, main))
+            })
+            .unwrap_or_else(|e: TargetError| panic!("Invalid java target: {}", e));
+
+        Self {
+            exec,
+            refs: Some(refs),
+            main,
+            criterion: config.criterion.clone(),
+            buf: String::new(),
+        }
+    }
+
+    fn check(&mut self, candidate: &str) -> Verdict {
+        self.buf.clear();
+        self.buf.push_str(candidate);
+        self.buf.push('\n');
+
+        let refs = self.refs.as_ref().unwrap();
+        let (buf, main) = (&self.buf, self.main);
+        let response = self
+            .exec
+            .with_attached::<_, _, Error>(|jni_env| {
+                let _stdio = STDIO.lock().unwrap_or_else(|e| e.into_inner());
+                Ok(run_main(jni_env, refs, main, buf.as_bytes())
+                    .map_err(|e| runtime::clear_exception(jni_env, e)))
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|response| response);
+
+        match response {
+            Ok(response) => self.criterion.judge(&response),
+            // e.g. the exception's toString threw
+            Err(reason) => Verdict::Error(reason),
+        }
+    }
+
+    fn close(self) {}
+}
+
+impl Drop for MainRunner {
+    fn drop(&mut self) {
+        // the global references need the thread to be attached to be released
+        drop(self.refs.take());
+        // SAFETY: every local reference of this thread lived in an executor frame,
+        // which are all gone by now.
+        unsafe { runtime::detach_current_thread() }
+    }
+}
+
+/// Calls `main` with `input` on `System.in`. The original streams are put back afterwards,
+/// even if the call failed.
+fn run_main(
+    jni_env: &mut JNIEnv,
+    refs: &Refs,
+    main: JStaticMethodID,
+    input: &[u8],
+) -> Result<Response, Error> {
+    // new ByteArrayInputStream(input)
+    let input = jni_env.byte_array_from_slice(input)?;
+    let stdin = jni_env.new_object("java/io/ByteArrayInputStream", "([B)V", &[(&input).into()])?;
+    // new PrintStream(new ByteArrayOutputStream(), true) for stdout & stderr
+    let (stdout, stdout_stream) = capture(jni_env)?;
+    let (stderr, stderr_stream) = capture(jni_env)?;
+
+    let original_in = jni_env.get_static_field(SYSTEM, "in", INPUT_STREAM)?.l()?;
+    let original_out = jni_env.get_static_field(SYSTEM, "out", PRINT_STREAM)?.l()?;
+    let original_err = jni_env.get_static_field(SYSTEM, "err", PRINT_STREAM)?.l()?;
+
+    let ran = redirect(jni_env, &stdin, &stdout_stream, &stderr_stream).and_then(|_| {
+        let class: &JClass = refs.class.as_obj().into();
+        // SAFETY: the ID belongs to the class & has the main signature
+        let (result, exit_code) = runtime::guard_exit(|| unsafe {
+            jni_env.call_static_method_unchecked(
+                class,
+                main,
+                ReturnType::Primitive(Primitive::Void),
+                &[JValue::from(refs.args.as_obj()).as_jni()],
+            )
+        });
+        match (result, exit_code) {
+            (Ok(_), _) => Ok(Ending::Returned),
+            (Err(Error::JavaException), Some(exit_code)) => {
+                jni_env.exception_clear()?;
+                Ok(Ending::Exited(exit_code))
+            }
+            // an uncaught exception ends `main`, like it would end a `java` process
+            (Err(Error::JavaException), None) => {
+                let exception = jni_env.exception_occurred()?;
+                jni_env.exception_clear()?;
+                Ok(Ending::Threw(exception))
+            }
+            (Err(e), _) => Err(e),
+        }
+    });
+    // the put back would fail with the exception of a failed call pending,
+    // so it's thrown again afterwards
+    let pending = match ran {
+        Err(_) if jni_env.exception_check()? => {
+            let exception = jni_env.exception_occurred()?;
+            jni_env.exception_clear()?;
+            Some(exception)
+        }
+        _ => None,
+    };
+    redirect(jni_env, &original_in, &original_out, &original_err)?;
+    if let Some(exception) = pending {
+        jni_env.throw(exception)?;
+    }
+    let ending = ran?;
+
+    let stdout = to_string(jni_env, &stdout)?;
+    let mut stderr = to_string(jni_env, &stderr)?;
+    let exit_code = match ending {
+        Ending::Returned => 0,
+        Ending::Exited(exit_code) => exit_code,
+        Ending::Threw(exception) => {
+            stderr.push_str(&to_string(jni_env, &exception)?);
+            1
+        }
+    };
+    Ok(Response {
+        stdout,
+        stderr,
+        exit_code: Some(exit_code),
+        truncated: false,
+    })
+}
+
+/// How a call of `main` ended.
+enum Ending<'local> {
+    Returned,
+    /// `System.exit` or `Runtime.halt` with the code.
+    Exited(i32),
+    /// An uncaught exception.
+    Threw(JThrowable<'local>),
+}
+
+/// Returns a fresh `ByteArrayOutputStream` & an auto flushing `PrintStream` writing into it.
+fn capture<'local>(
+    jni_env: &mut JNIEnv<'local>,
+) -> Result<(JObject<'local>, JObject<'local>), Error> {
+    let buffer = jni_env.new_object("java/io/ByteArrayOutputStream", "()V", &[])?;
+    let stream = jni_env.new_object(
+        "java/io/PrintStream",
+        "(Ljava/io/OutputStream;Z)V",
+        &[(&buffer).into(), true.into()],
+    )?;
+    Ok((buffer, stream))
+}
+
+fn redirect(
+    jni_env: &mut JNIEnv,
+    stdin: &JObject,
+    stdout: &JObject,
+    stderr: &JObject,
+) -> Result<(), Error> {
+    let set_in = format!("({})V", INPUT_STREAM);
+    let set_out = format!("({})V", PRINT_STREAM);
+    jni_env.call_static_method(SYSTEM, "setIn", &set_in, &[stdin.into()])?;
+    jni_env.call_static_method(SYSTEM, "setOut", &set_out, &[stdout.into()])?;
+    jni_env.call_static_method(SYSTEM, "setErr", &set_out, &[stderr.into()])?;
+    Ok(())
+}
+
+fn to_string(jni_env: &mut JNIEnv, object: &JObject) -> Result<String, Error> {
+    let string = jni_env
+        .call_method(object, "toString", "()Ljava/lang/String;", &[])?
+        .l()?;
+    Ok(jni_env.get_string(&JString::from(string))?.into())
+}
@@ -1,9 +1,49 @@
+mod child_jvm;
 pub mod crack;
-mod indices;
-mod internal;
+pub(crate) mod indices;
+mod main_runner;
 pub mod param_interface;
-mod result;
-mod tasks;
+pub mod runtime;
+mod target;
+mod wrapper;
 
-pub use crack::crack;
+pub use child_jvm::{ChildJvm, ChildJvmConfig};
+pub use crack::{crack, start};
+pub use main_runner::{MainRunner, MainRunnerConfig};
 pub use param_interface::CrackParam;
+pub use target::{JniIsolation, JniMethod, JniMode, JniTarget, TargetError, MAIN_SIG};
+pub use wrapper::{PasswordWrapper, PasswordWrapperConfig};
+
+#[cfg(test)]
+pub(crate) mod tests_jni {
+    use std::{fs, path::Path, process::Command, sync::Mutex};
+
+    /// Tests compiling the same class mustn't write its source at the same time.
+    static COMPILE: Mutex<()> = Mutex::new(());
+
+    /// Compiles a test class into `target/tests_java`, which is the classpath of every test
+    /// target, as the shared VM keeps the classpath it started with.
+    /// None without a JDK, the JVM tests are skipped then.
+    pub(crate) fn compile(class: &str, source: &str) -> Option<String> {
+        let _compile = COMPILE.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/tests_java");
+        let source_path = dir.join("src").join(format!("{}.java", class));
+        fs::create_dir_all(source_path.parent().unwrap()).unwrap();
+        fs::write(&source_path, source).unwrap();
+        let Ok(output) = Command::new("java")
+            .args(["-m", "jdk.compiler/com.sun.tools.javac.Main", "-d"])
+            .arg(&dir)
+            .arg(&source_path)
+            .output()
+        else {
+            eprintln!("No JDK found, skipping the test.");
+            return None;
+        };
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Some(dir.to_string_lossy().into_owned())
+    }
+}
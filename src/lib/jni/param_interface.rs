@@ -1,49 +1,114 @@
-use crate::symbols::combination_count;
+use std::io;
+
+use crate::attack::Attack;
+use crate::engine::{Search, WorkerOptions};
+use crate::jni::target::JniTarget;
+use crate::oracle::{Matcher, SuccessCriterion};
+use crate::symbols::KeyspaceOverflow;
 
 #[derive(Debug, Clone)]
 pub struct CrackParam {
-    charset: Box<[char]>,
-    min_length: u8,
-    max_length: u8,
-    total_combos: usize,
+    search: Search,
     output_contains: String,
+    criterion: SuccessCriterion,
+    target: JniTarget,
 }
 
 impl CrackParam {
+    /// By default a candidate is correct if the response contains `output_contains`.
     pub fn new(
         charset: Box<[char]>,
         min_length: u8,
         max_length: u8,
         output_contains: &str,
     ) -> Self {
-        let total_combos = combination_count(&charset, min_length, max_length);
-
         Self {
-            charset,
-            min_length,
-            max_length,
-            total_combos,
+            search: Search::new(charset, min_length, max_length),
             output_contains: String::from(output_contains),
+            criterion: SuccessCriterion::StdoutMatches(Matcher::literal(output_contains)),
+            target: JniTarget::default(),
         }
     }
 
+    /// Replaces the default response match.
+    pub fn with_criterion(mut self, criterion: SuccessCriterion) -> Self {
+        self.criterion = criterion;
+        self
+    }
+
+    /// Sets the java class to crack through, see `JniTarget`.
+    pub fn with_target(mut self, target: JniTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Replaces the brute force over the charset, e.g. with a wordlist.
+    pub fn with_attack(mut self, attack: Attack) -> Self {
+        self.search = self.search.with_attack(attack);
+        self
+    }
+
+    /// Replaces the default of one worker per CPU but one, neither pinned nor at low priority.
+    pub fn with_worker_options(mut self, worker_options: WorkerOptions) -> Self {
+        self.search = self.search.with_worker_options(worker_options);
+        self
+    }
+
+    /// A candidate is correct if the response does *not* match the failure pattern.
+    /// Empty or truncated responses are reported as oracle errors.
+    pub fn with_failure_pattern(self, failure: Matcher) -> Self {
+        self.with_criterion(SuccessCriterion::StdoutLacks(failure))
+    }
+
     pub const fn charset(&self) -> &[char] {
-        &self.charset
+        self.search.charset()
     }
 
     pub const fn max_length(&self) -> u8 {
-        self.max_length
+        self.search.max_length()
     }
 
     pub const fn min_length(&self) -> u8 {
-        self.min_length
+        self.search.min_length()
+    }
+
+    /// Number of combinations of the charset between the min & max length.
+    pub fn total_combos(&self) -> Result<u128, KeyspaceOverflow> {
+        self.search.total_combos()
+    }
+
+    /// The candidate at index `n` of the attack, see `Attack::nth_candidate`.
+    pub fn nth_candidate(&self, n: u128) -> io::Result<Option<String>> {
+        self.search.nth_candidate(n)
     }
 
-    pub fn total_combos(&self) -> usize {
-        self.total_combos
+    /// The index of `candidate` in the attack, see `Attack::candidate_index`.
+    pub fn candidate_index(&self, candidate: &str) -> io::Result<Option<u128>> {
+        self.search.candidate_index(candidate)
     }
 
     pub fn output_contains(&self) -> &String {
         &self.output_contains
     }
+
+    pub fn criterion(&self) -> &SuccessCriterion {
+        &self.criterion
+    }
+
+    pub fn target(&self) -> &JniTarget {
+        &self.target
+    }
+
+    pub fn attack(&self) -> &Attack {
+        self.search.attack()
+    }
+
+    pub fn worker_options(&self) -> &WorkerOptions {
+        self.search.worker_options()
+    }
+
+    /// What the engine searches through, without the target.
+    pub fn search(&self) -> &Search {
+        &self.search
+    }
 }
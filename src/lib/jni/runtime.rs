@@ -0,0 +1,242 @@
+//! The JNI spec allows only one JavaVM per process, so all workers share this one.
+//! It is created lazily on first use & lives until the process exits.
+//!
+//! Target code calling `System.exit` or `Runtime.halt` would end the whole process,
+//! so both get intercepted while a worker calls into the target, see `guard_exit`.
+
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use jni::{
+    errors::Error,
+    objects::{JClass, JString},
+    sys::jint,
+    Executor, JNIEnv, JavaVM, NativeMethod,
+};
+use log::{debug, warn};
+
+use crate::jni::target::{JniTarget, TargetError};
+
+static JVM: OnceLock<Arc<JavaVM>> = OnceLock::new();
+/// Serializes the creation & remembers the classpath & options the VM got created with.
+static INIT: Mutex<Option<(Vec<String>, Vec<String>)>> = Mutex::new(None);
+
+thread_local! {
+    /// Set while the thread runs target code inside `guard_exit`.
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    /// The code of the last intercepted exit attempt.
+    static EXIT_CODE: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+/// Returns the process wide VM. The first call creates it with the target's classpath &
+/// JVM options. A VM can't be restarted, so later calls with different options get the
+/// existing VM & a warning.
+pub fn jvm(target: &JniTarget) -> Result<&'static Arc<JavaVM>, TargetError> {
+    let mut init = INIT.lock().unwrap_or_else(|e| e.into_inner());
+    let options = (target.classpath().to_vec(), target.jvm_options().to_vec());
+
+    if let Some(jvm) = JVM.get() {
+        if let Some(created_with) = init
+            .as_ref()
+            .filter(|created_with| **created_with != options)
+        {
+            warn!(
+                "The JVM is already running with classpath {:?} & options {:?}, ignoring {:?} & {:?}.",
+                created_with.0, created_with.1, options.0, options.1
+            );
+        }
+        return Ok(jvm);
+    }
+
+    let jvm = JavaVM::new(target.init_args()?)?;
+    intercept_exit(&jvm)?;
+    *init = Some(options);
+    Ok(JVM.get_or_init(|| Arc::new(jvm)))
+}
+
+/// Executor on the shared VM. Every `with_attached` call runs in its own local frame,
+/// so local references are freed after each call.
+pub fn executor(target: &JniTarget) -> Result<Executor, TargetError> {
+    Ok(Executor::new(jvm(target)?.clone()))
+}
+
+/// Makes sure the target's class & methods exist, before any worker starts.
+/// The calling thread is only attached for the duration of the check.
+pub fn check_target(target: &JniTarget) -> Result<(), TargetError> {
+    let mut env = jvm(target)?.attach_current_thread()?;
+    target.validate(&mut env)?;
+    Ok(())
+}
+
+/// Detaches the current thread from the shared VM, if there is one.
+///
+/// # Safety
+///
+/// No local reference created on this thread may be used afterwards.
+pub(crate) unsafe fn detach_current_thread() {
+    if let Some(jvm) = JVM.get() {
+        jvm.detach_current_thread();
+    }
+}
+
+/// Clears the exception a failed call into the target left pending, if any, so that the
+/// worker can go on with the next candidate. Describes the failure, with the exception's
+/// `toString` if there was one.
+pub(crate) fn clear_exception(env: &mut JNIEnv, e: Error) -> String {
+    let Ok(true) = env.exception_check() else {
+        return e.to_string();
+    };
+    let exception = env.exception_occurred();
+    let _ = env.exception_clear();
+    let description = exception.ok().and_then(|exception| {
+        let string = env
+            .call_method(&exception, "toString", "()Ljava/lang/String;", &[])
+            .ok()?
+            .l()
+            .ok()?;
+        env.get_string(&JString::from(string))
+            .ok()
+            .map(String::from)
+    });
+    // toString may have thrown as well
+    let _ = env.exception_clear();
+    description.unwrap_or_else(|| e.to_string())
+}
+
+/// Runs `f` with exit attempts of the target turned into a thrown `java.lang.Error`,
+/// so that the call returns with a pending exception instead of ending the process.
+/// Returns the exit code, if the target tried to exit. The caller has to clear the exception.
+///
+/// Only the final halt is intercepted. `Shutdown.exit` runs the shutdown hooks & the
+/// `deleteOnExit` deletions & marks the VM as shut down before it halts, so that happens on
+/// the first exit attempt for good & `Runtime.addShutdownHook` throws afterwards.
+/// Exits from threads that aren't in `guard_exit`, e.g. ones the target started, still end
+/// the process.
+pub(crate) fn guard_exit<T>(f: impl FnOnce() -> T) -> (T, Option<i32>) {
+    EXIT_CODE.with(|code| code.set(None));
+    GUARDED.with(|guarded| guarded.set(true));
+    let result = f();
+    GUARDED.with(|guarded| guarded.set(false));
+    (result, EXIT_CODE.with(Cell::take))
+}
+
+/// `Runtime.exit` & `Runtime.halt` both end in the native `Shutdown.halt0`,
+/// which gets replaced by `halt0`.
+fn intercept_exit(jvm: &JavaVM) -> Result<(), TargetError> {
+    let mut env = jvm.attach_current_thread()?;
+    let shutdown = env.find_class("java/lang/Shutdown")?;
+    env.register_native_methods(
+        &shutdown,
+        &[NativeMethod {
+            name: "halt0".into(),
+            sig: "(I)V".into(),
+            fn_ptr: halt0 as *mut c_void,
+        }],
+    )?;
+    Ok(())
+}
+
+/// Replacement of `Shutdown.halt0(int)`. Outside of `guard_exit`, e.g. in the JVM's signal
+/// handler thread, it exits the process like the original.
+extern "system" fn halt0(mut env: JNIEnv, _class: JClass, status: jint) {
+    if !GUARDED.with(Cell::get) {
+        std::process::exit(status);
+    }
+    debug!("Intercepted exit with code {}.", status);
+    EXIT_CODE.with(|code| code.set(Some(status)));
+    if env
+        .throw_new(
+            "java/lang/Error",
+            format!("target exited with code {}", status),
+        )
+        .is_err()
+    {
+        std::process::exit(status);
+    }
+}
+
+#[cfg(test)]
+mod tests_runtime {
+    use crate::jni::{tests_jni::compile, JniTarget, MainRunner, MainRunnerConfig};
+    use crate::oracle::{Matcher, Oracle, SuccessCriterion, Verdict};
+
+    /// Exits with the code it reads, or registers a shutdown hook for "hook".
+    const EXIT: &str = r#"
+        public class RuntimeExit {
+            public static void main(String[] args) throws Exception {
+                String line = new java.io.BufferedReader(
+                    new java.io.InputStreamReader(System.in)).readLine();
+                if (line.equals("hook")) {
+                    Runtime.getRuntime().addShutdownHook(new Thread());
+                    return;
+                }
+                System.exit(Integer.parseInt(line));
+            }
+        }"#;
+
+    #[test]
+    fn test_exit_twice() {
+        let Some(classpath) = compile("RuntimeExit", EXIT) else {
+            return;
+        };
+        let config = MainRunnerConfig {
+            target: JniTarget::new()
+                .with_classpath(&classpath)
+                .with_main_class("RuntimeExit"),
+            criterion: SuccessCriterion::ExitCode(3),
+        };
+        let mut oracle = MainRunner::create(&config, 0);
+        assert_eq!(oracle.check("3"), Verdict::Correct);
+        // the VM counts as shut down from the first exit on, the second one is a plain halt
+        assert_eq!(oracle.check("3"), Verdict::Correct);
+        assert_eq!(oracle.check("4"), Verdict::Incorrect);
+        oracle.close();
+
+        // the documented limit, registering a hook throws after an intercepted exit
+        let config = MainRunnerConfig {
+            criterion: SuccessCriterion::ExitCode(1),
+            ..config
+        };
+        let mut oracle = MainRunner::create(&config, 0);
+        assert_eq!(oracle.check("hook"), Verdict::Correct);
+        oracle.close();
+    }
+
+    /// Throws an exception that can't even be described for "throw", prints the line otherwise.
+    const FAULTY: &str = r#"
+        public class RuntimeFaulty {
+            public static void main(String[] args) throws Exception {
+                String line = new java.io.BufferedReader(
+                    new java.io.InputStreamReader(System.in)).readLine();
+                if (line.equals("throw")) {
+                    throw new RuntimeException() {
+                        public String toString() {
+                            throw new IllegalStateException("broken");
+                        }
+                    };
+                }
+                System.out.println(line);
+            }
+        }"#;
+
+    #[test]
+    fn test_main_error() {
+        let Some(classpath) = compile("RuntimeFaulty", FAULTY) else {
+            return;
+        };
+        let config = MainRunnerConfig {
+            target: JniTarget::new()
+                .with_classpath(&classpath)
+                .with_main_class("RuntimeFaulty"),
+            criterion: SuccessCriterion::StdoutMatches(Matcher::literal("ok")),
+        };
+        let mut oracle = MainRunner::create(&config, 0);
+        assert!(
+            matches!(oracle.check("throw"), Verdict::Error(reason) if reason.contains("broken"))
+        );
+        // nothing is left pending & the streams are back for the next candidate
+        assert_eq!(oracle.check("ok"), Verdict::Correct);
+        oracle.close();
+    }
+}
@@ -0,0 +1,497 @@
+use std::{env, ffi::OsString, time::Duration};
+
+use jni::{
+    objects::{JClass, JMethodID, JStaticMethodID},
+    signature::{JavaType, Primitive, ReturnType, TypeSignature},
+    InitArgs, InitArgsBuilder, JNIEnv, JNIVersion,
+};
+use thiserror::Error;
+
+/// Errors while setting up the java target.
+#[derive(Debug, Error)]
+pub enum TargetError {
+    #[error("classpath entry {0:?} contains a path separator")]
+    InvalidClasspath(String),
+    #[error("invalid JVM options: {0}")]
+    InvalidOptions(String),
+    #[error("class {class} not found on classpath {classpath:?}")]
+    MissingClass {
+        class: String,
+        classpath: Vec<String>,
+    },
+    #[error("method {class}.{name}{sig} not found")]
+    MissingMethod {
+        class: String,
+        name: String,
+        sig: String,
+    },
+    #[error("method {class}.{name}{sig} has to be {expected}")]
+    InvalidSignature {
+        class: String,
+        name: String,
+        sig: String,
+        expected: &'static str,
+    },
+    #[error("starting the JVM failed: {0}")]
+    StartJvm(#[from] jni::errors::StartJvmError),
+    #[error("starting the java child failed: {0}")]
+    SpawnChild(#[from] std::io::Error),
+    #[error("the java child failed: {0}")]
+    ChildFailed(String),
+    #[error("calling java failed: {0}")]
+    Jni(#[from] jni::errors::Error),
+}
+
+/// Name & signature of a java method, e.g. `writePipe` & `([BII)V`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JniMethod {
+    name: String,
+    sig: String,
+}
+
+impl JniMethod {
+    pub fn new(name: &str, sig: &str) -> Self {
+        Self {
+            name: name.into(),
+            sig: sig.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sig(&self) -> &str {
+        &self.sig
+    }
+
+    /// Makes sure the signature is what the wrapper calls the method with,
+    /// as the calls are unchecked.
+    fn check(&self, shape: Shape, class_name: &str) -> Result<(), TargetError> {
+        let sig = TypeSignature::from_str(&self.sig)?;
+        if shape.allows(&sig) {
+            return Ok(());
+        }
+        Err(TargetError::InvalidSignature {
+            class: class_name.into(),
+            name: self.name.clone(),
+            sig: self.sig.clone(),
+            expected: shape.expected(),
+        })
+    }
+
+    /// Looks up the method on the class, so that it can be called without the name & signature.
+    fn resolve(
+        &self,
+        env: &mut JNIEnv,
+        class: &JClass,
+        class_name: &str,
+    ) -> Result<ResolvedMethod, TargetError> {
+        let id = env
+            .get_method_id(class, &self.name, &self.sig)
+            .map_err(|e| {
+                clear_exception(env);
+                match e {
+                    jni::errors::Error::MethodNotFound { .. }
+                    | jni::errors::Error::JavaException => TargetError::MissingMethod {
+                        class: class_name.into(),
+                        name: self.name.clone(),
+                        sig: self.sig.clone(),
+                    },
+                    e => e.into(),
+                }
+            })?;
+        let ret = TypeSignature::from_str(&self.sig)?.ret;
+        Ok(ResolvedMethod { id, ret })
+    }
+}
+
+/// The arguments the wrapper passes to a method & the result it expects back.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Constructor,
+    Write,
+    Read,
+    Close,
+}
+
+impl Shape {
+    fn allows(self, sig: &TypeSignature) -> bool {
+        let void = ReturnType::Primitive(Primitive::Void);
+        match self {
+            Shape::Constructor => sig.args.is_empty() && sig.ret == void,
+            Shape::Write => {
+                sig.args
+                    == [
+                        JavaType::Array(Box::new(JavaType::Primitive(Primitive::Byte))),
+                        JavaType::Primitive(Primitive::Int),
+                        JavaType::Primitive(Primitive::Int),
+                    ]
+                    && sig.ret == void
+            }
+            // the string is checked when the response is read
+            Shape::Read => sig.args.is_empty() && sig.ret == ReturnType::Object,
+            Shape::Close => sig.args.is_empty(),
+        }
+    }
+
+    fn expected(self) -> &'static str {
+        match self {
+            Shape::Constructor => "()V",
+            Shape::Write => "([BII)V",
+            Shape::Read => "without arguments returning a String",
+            Shape::Close => "without arguments",
+        }
+    }
+}
+
+/// Method ID & return type of a looked up method.
+/// The ID stays valid as long as its class is loaded.
+#[derive(Debug, Clone)]
+pub(crate) struct ResolvedMethod {
+    pub id: JMethodID,
+    pub ret: ReturnType,
+}
+
+/// A validated target with all of its methods looked up.
+pub(crate) struct ResolvedTarget<'local> {
+    pub class: JClass<'local>,
+    pub constructor: ResolvedMethod,
+    pub write: ResolvedMethod,
+    pub read: ResolvedMethod,
+    pub close: ResolvedMethod,
+}
+
+/// Signature of a java entry point, `public static void main(String[] args)`.
+pub const MAIN_SIG: &str = "([Ljava/lang/String;)V";
+
+/// How the target class is driven.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JniMode {
+    /// An instance of a wrapper class with write, read & close methods.
+    #[default]
+    Wrapper,
+    /// The class's `main`, called once per candidate with the candidate on `System.in`.
+    /// `System.out` & `System.err` are captured as the response.
+    ///
+    /// In this process, `System.exit` & `Runtime.halt` end the call instead of the process
+    /// only as far as the JVM allows, see `runtime::guard_exit`: the first exit still runs
+    /// the shutdown hooks & marks the VM as shutting down, so `Runtime.addShutdownHook`
+    /// throws from then on, & an exit from a thread the target started ends the whole process.
+    /// `JniIsolation::ChildJvm` has none of these limits.
+    Main,
+}
+
+/// Where the target class runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JniIsolation {
+    /// In the shared VM of this process. Fast, but a misbehaving class affects every worker.
+    #[default]
+    InProcess,
+    /// In a `java` child process per worker, see `ChildJvm`. Restarted after the class
+    /// exited & on timeout.
+    ChildJvm,
+}
+
+/// Where to find the java wrapper class & how to talk to it.
+/// The defaults describe the `PasswordWrapper` helper class.
+#[derive(Debug, Clone)]
+pub struct JniTarget {
+    classpath: Vec<String>,
+    jvm_options: Vec<String>,
+    mode: JniMode,
+    main_args: Vec<String>,
+    isolation: JniIsolation,
+    java: String,
+    timeout: Duration,
+    class_name: String,
+    constructor_sig: String,
+    write: JniMethod,
+    read: JniMethod,
+    close: JniMethod,
+}
+
+impl Default for JniTarget {
+    fn default() -> Self {
+        Self {
+            classpath: vec![],
+            jvm_options: vec!["-Xcheck:jni".into()],
+            mode: JniMode::default(),
+            main_args: vec![],
+            isolation: JniIsolation::default(),
+            java: "java".into(),
+            timeout: Duration::from_secs(10),
+            class_name: "PasswordWrapper".into(),
+            constructor_sig: "()V".into(),
+            write: JniMethod::new("writePipe", "([BII)V"),
+            read: JniMethod::new("spawn", "()Ljava/lang/String;"),
+            close: JniMethod::new("closePipe", "()V"),
+        }
+    }
+}
+
+impl JniTarget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory or jar to the classpath.
+    pub fn with_classpath(mut self, entry: &str) -> Self {
+        self.classpath.push(entry.into());
+        self
+    }
+
+    /// Adds a raw JVM option, e.g. `-Xmx512m`.
+    pub fn with_jvm_option(mut self, option: &str) -> Self {
+        self.jvm_options.push(option.into());
+        self
+    }
+
+    /// Runs `main` of the class in JNI notation, e.g. `com/example/Login`, instead of a wrapper.
+    pub fn with_main_class(mut self, class_name: &str) -> Self {
+        self.class_name = class_name.into();
+        self.mode = JniMode::Main;
+        self
+    }
+
+    /// Adds a command line argument passed to `main`.
+    pub fn with_main_arg(mut self, arg: &str) -> Self {
+        self.main_args.push(arg.into());
+        self
+    }
+
+    /// Sets where the target class runs.
+    pub fn with_isolation(mut self, isolation: JniIsolation) -> Self {
+        self.isolation = isolation;
+        self
+    }
+
+    /// The `java` executable for child JVMs, `java` on the path by default.
+    pub fn with_java(mut self, java: &str) -> Self {
+        self.java = java.into();
+        self
+    }
+
+    /// How long a child JVM may take to answer a candidate, including its startup.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Class name in JNI notation, e.g. `com/example/Wrapper`.
+    pub fn with_class(mut self, class_name: &str, constructor_sig: &str) -> Self {
+        self.class_name = class_name.into();
+        self.constructor_sig = constructor_sig.into();
+        self
+    }
+
+    /// Method that receives the candidate as `(byte[] bytes, int offset, int length)`.
+    pub fn with_write(mut self, name: &str, sig: &str) -> Self {
+        self.write = JniMethod::new(name, sig);
+        self
+    }
+
+    /// Method without arguments that returns the target's response as a string.
+    pub fn with_read(mut self, name: &str, sig: &str) -> Self {
+        self.read = JniMethod::new(name, sig);
+        self
+    }
+
+    /// Method without arguments that is called once a worker is done.
+    pub fn with_close(mut self, name: &str, sig: &str) -> Self {
+        self.close = JniMethod::new(name, sig);
+        self
+    }
+
+    pub fn classpath(&self) -> &[String] {
+        &self.classpath
+    }
+
+    pub fn jvm_options(&self) -> &[String] {
+        &self.jvm_options
+    }
+
+    pub fn mode(&self) -> JniMode {
+        self.mode
+    }
+
+    pub fn main_args(&self) -> &[String] {
+        &self.main_args
+    }
+
+    pub fn isolation(&self) -> JniIsolation {
+        self.isolation
+    }
+
+    pub fn java(&self) -> &str {
+        &self.java
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    pub fn constructor_sig(&self) -> &str {
+        &self.constructor_sig
+    }
+
+    pub fn write(&self) -> &JniMethod {
+        &self.write
+    }
+
+    pub fn read(&self) -> &JniMethod {
+        &self.read
+    }
+
+    pub fn close(&self) -> &JniMethod {
+        &self.close
+    }
+
+    /// Joins the classpath entries with the platform's separator.
+    pub fn classpath_string(&self) -> Result<OsString, TargetError> {
+        if let Some(entry) = self
+            .classpath
+            .iter()
+            .find(|entry| env::join_paths([entry]).is_err())
+        {
+            return Err(TargetError::InvalidClasspath(entry.clone()));
+        }
+        Ok(env::join_paths(&self.classpath).expect("Entries were checked."))
+    }
+
+    /// Builds the JVM arguments from the classpath & the extra options.
+    pub fn init_args(&self) -> Result<InitArgs<'_>, TargetError> {
+        let mut builder = InitArgsBuilder::new().version(JNIVersion::V8);
+        if !self.classpath.is_empty() {
+            let classpath = self.classpath_string()?;
+            builder = builder.option(format!("-Djava.class.path={}", classpath.to_string_lossy()));
+        }
+        for option in &self.jvm_options {
+            builder = builder.option(option);
+        }
+        builder
+            .build()
+            .map_err(|e| TargetError::InvalidOptions(e.to_string()))
+    }
+
+    /// Looks up the class, its constructor & every method, so that a typo shows up before
+    /// the first candidate instead of as a failed call in the middle of the run.
+    pub fn validate<'local>(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<JClass<'local>, TargetError> {
+        match self.mode {
+            JniMode::Wrapper => Ok(self.resolve(env)?.class),
+            JniMode::Main => Ok(self.resolve_main(env)?.0),
+        }
+    }
+
+    /// Looks up the class & its static `main`.
+    pub(crate) fn resolve_main<'local>(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<(JClass<'local>, JStaticMethodID), TargetError> {
+        let class = self.find_class(env)?;
+        let main = env
+            .get_static_method_id(&class, "main", MAIN_SIG)
+            .map_err(|e| {
+                clear_exception(env);
+                match e {
+                    jni::errors::Error::MethodNotFound { .. }
+                    | jni::errors::Error::JavaException => TargetError::MissingMethod {
+                        class: self.class_name.clone(),
+                        name: "main".into(),
+                        sig: MAIN_SIG.into(),
+                    },
+                    e => e.into(),
+                }
+            })?;
+        Ok((class, main))
+    }
+
+    /// Like `validate`, but keeps the method IDs for unchecked calls.
+    pub(crate) fn resolve<'local>(
+        &self,
+        env: &mut JNIEnv<'local>,
+    ) -> Result<ResolvedTarget<'local>, TargetError> {
+        self.check_signatures()?;
+        let class = self.find_class(env)?;
+
+        let constructor = JniMethod::new("<init>", &self.constructor_sig);
+        let constructor = constructor.resolve(env, &class, &self.class_name)?;
+        let write = self.write.resolve(env, &class, &self.class_name)?;
+        let read = self.read.resolve(env, &class, &self.class_name)?;
+        let close = self.close.resolve(env, &class, &self.class_name)?;
+        Ok(ResolvedTarget {
+            class,
+            constructor,
+            write,
+            read,
+            close,
+        })
+    }
+
+    /// Rejects signatures the wrapper can't call, before the VM gets involved.
+    pub(crate) fn check_signatures(&self) -> Result<(), TargetError> {
+        JniMethod::new("<init>", &self.constructor_sig)
+            .check(Shape::Constructor, &self.class_name)?;
+        self.write.check(Shape::Write, &self.class_name)?;
+        self.read.check(Shape::Read, &self.class_name)?;
+        self.close.check(Shape::Close, &self.class_name)
+    }
+
+    fn find_class<'local>(&self, env: &mut JNIEnv<'local>) -> Result<JClass<'local>, TargetError> {
+        env.find_class(&self.class_name).map_err(|e| {
+            clear_exception(env);
+            match e {
+                jni::errors::Error::JavaException => TargetError::MissingClass {
+                    class: self.class_name.clone(),
+                    classpath: self.classpath.clone(),
+                },
+                e => e.into(),
+            }
+        })
+    }
+}
+
+/// A failed lookup leaves a pending `NoClassDefFoundError`/`NoSuchMethodError` behind.
+fn clear_exception(env: &JNIEnv) {
+    if env.exception_check().unwrap_or(false) {
+        let _ = env.exception_clear();
+    }
+}
+
+#[cfg(test)]
+mod tests_target {
+    use super::*;
+
+    #[test]
+    fn test_check_signatures() {
+        assert!(JniTarget::new().check_signatures().is_ok());
+
+        let target = JniTarget::new().with_write("x", "(Ljava/lang/String;)V");
+        assert!(matches!(
+            target.check_signatures(),
+            Err(TargetError::InvalidSignature { name, expected: "([BII)V", .. }) if name == "x"
+        ));
+        let target = JniTarget::new().with_read("spawn", "()I");
+        assert!(matches!(
+            target.check_signatures(),
+            Err(TargetError::InvalidSignature { .. })
+        ));
+        let target = JniTarget::new().with_close("closePipe", "(I)V");
+        assert!(matches!(
+            target.check_signatures(),
+            Err(TargetError::InvalidSignature { .. })
+        ));
+        let target = JniTarget::new().with_class("PasswordWrapper", "(Ljava/lang/String;)V");
+        assert!(matches!(
+            target.check_signatures(),
+            Err(TargetError::InvalidSignature { .. })
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use jni::{
+    errors::Error,
+    objects::{GlobalRef, JByteArray, JString, JValue},
+    sys::jbyte,
+    Executor, JNIEnv,
+};
+use log::warn;
+
+use crate::jni::{
+    runtime,
+    target::{JniTarget, ResolvedMethod, TargetError},
+};
+use crate::oracle::{Oracle, Response, SuccessCriterion, Verdict};
+
+/// Initial size of the byte array the candidates are copied into; it grows as needed.
+const BUFFER_LEN: usize = 64;
+
+/// Everything the `PasswordWrapper` oracle needs from the crack parameters.
+#[derive(Debug, Clone)]
+pub struct PasswordWrapperConfig {
+    pub target: JniTarget,
+    /// Judges the string the read method returns; it counts as the target's stdout.
+    pub criterion: SuccessCriterion,
+}
+
+/// Oracle backed by a java wrapper class, by default the `PasswordWrapper` helper.
+/// Each candidate is passed to the write method, the read method returns the target's response.
+/// All instances share the process wide VM; the worker thread detaches once the instance is dropped.
+///
+/// The class, the method IDs & the byte array are looked up once per worker,
+/// every attempt only copies the candidate & makes two unchecked calls.
+/// If the target tries to exit, the response has no output & the exit code.
+/// An exception or a null response is an oracle error, the worker goes on with the next candidate.
+/// `create` panics if the target is invalid, `start` rules that out before any worker runs.
+pub struct PasswordWrapper {
+    exec: Executor,
+    // an Option, so that the references can be released before the thread detaches
+    refs: Option<Refs>,
+    write: ResolvedMethod,
+    read: ResolvedMethod,
+    close: ResolvedMethod,
+    criterion: SuccessCriterion,
+    // reused for every candidate, so that appending the newline doesn't allocate
+    buf: Vec<jbyte>,
+}
+
+/// Global references a worker holds on to between attempts.
+struct Refs {
+    // keeps the class loaded, so that the method IDs stay valid
+    _class: GlobalRef,
+    instance: GlobalRef,
+    buffer: GlobalRef,
+    buffer_len: usize,
+}
+
+impl Oracle for PasswordWrapper {
+    type Config = PasswordWrapperConfig;
+
+    fn create(config: &Self::Config, _tid: usize) -> Self {
+        let target = &config.target;
+
+        // Attach the current thread to the shared JVM
+        let exec =
+            runtime::executor(target).unwrap_or_else(|e| panic!("Invalid java target: {}", e));
+
+        let (refs, [write, read, close]) = exec
+            .with_attached(|jni_env| {
+                // Make sure the class & all methods exist before the first candidate
+                let resolved = target.resolve(jni_env)?;
+
+                // Load java wrapper class at classpath jvm argument option
+                // SAFETY: `resolve` checked that the constructor takes no arguments
+                let instance = unsafe {
+                    jni_env.new_object_unchecked(&resolved.class, resolved.constructor.id, &[])
+                }?;
+                let buffer = jni_env.new_byte_array(BUFFER_LEN as i32)?;
+                let refs = Refs {
+                    _class: jni_env.new_global_ref(&resolved.class)?,
+                    instance: jni_env.new_global_ref(instance)?,
+                    buffer: jni_env.new_global_ref(buffer)?,
+                    buffer_len: BUFFER_LEN,
+                };
+                Ok((refs, [resolved.write, resolved.read, resolved.close]))
+            })
+            .unwrap_or_else(|e: TargetError| panic!("Invalid java target: {}", e));
+
+        Self {
+            exec,
+            refs: Some(refs),
+            write,
+            read,
+            close,
+            criterion: config.criterion.clone(),
+            buf: Vec::with_capacity(BUFFER_LEN),
+        }
+    }
+
+    fn check(&mut self, candidate: &str) -> Verdict {
+        self.buf.clear();
+        self.buf.extend(candidate.bytes().map(|byte| byte as jbyte));
+        self.buf.push(b'\n' as jbyte);
+
+        let refs = self.refs.as_mut().unwrap();
+        let (buf, write, read) = (&self.buf, &self.write, &self.read);
+        let response = self
+            .exec
+            .with_attached::<_, _, Error>(|jni_env| {
+                Ok(exchange(jni_env, refs, buf, write, read)
+                    .map_err(|e| runtime::clear_exception(jni_env, e)))
+            })
+            .map_err(|e| e.to_string())
+            .and_then(|response| response);
+
+        match response {
+            Ok(response) => self.criterion.judge(&response),
+            // e.g. the target threw or read returned null
+            Err(reason) => Verdict::Error(reason),
+        }
+    }
+
+    fn close(self) {
+        let refs = self.refs.as_ref().unwrap();
+        let closed = self.exec.with_attached::<_, _, Error>(|jni_env| {
+            // Call close()
+            // SAFETY: `resolve` checked that close takes no arguments
+            let closed = unsafe {
+                jni_env.call_method_unchecked(
+                    &refs.instance,
+                    self.close.id,
+                    self.close.ret.clone(),
+                    &[],
+                )
+            };
+            Ok(closed
+                .map(|_| ())
+                .map_err(|e| runtime::clear_exception(jni_env, e)))
+        });
+        if let Err(reason) = closed.map_err(|e| e.to_string()).and_then(|closed| closed) {
+            warn!("The target's close method failed: {}", reason);
+        }
+    }
+}
+
+/// Passes the candidate in `buf` to the write method & returns what the read method gives.
+fn exchange(
+    jni_env: &mut JNIEnv,
+    refs: &mut Refs,
+    buf: &[jbyte],
+    write: &ResolvedMethod,
+    read: &ResolvedMethod,
+) -> Result<Response, Error> {
+    // Grow the byte array for long candidates
+    if refs.buffer_len < buf.len() {
+        refs.buffer_len = buf.len().next_power_of_two();
+        let buffer = jni_env.new_byte_array(refs.buffer_len as i32)?;
+        refs.buffer = jni_env.new_global_ref(buffer)?;
+    }
+    let buffer: &JByteArray = refs.buffer.as_obj().into();
+    jni_env.set_byte_array_region(buffer, 0, buf)?;
+
+    // Call write(byte[] bytes, int offset, int length)
+    let args = [
+        JValue::from(refs.buffer.as_obj()).as_jni(),
+        JValue::from(0).as_jni(),
+        JValue::from(buf.len() as i32).as_jni(),
+    ];
+    let (response, exit_code) = runtime::guard_exit(|| {
+        // SAFETY: the IDs belong to the instance's class & `resolve` checked that
+        // write takes (byte[], int, int) & read takes no arguments & returns an object
+        unsafe {
+            jni_env.call_method_unchecked(&refs.instance, write.id, write.ret.clone(), &args)
+        }?;
+
+        // Call read() -> String
+        unsafe { jni_env.call_method_unchecked(&refs.instance, read.id, read.ret.clone(), &[]) }?
+            .l()
+    });
+    if let Some(exit_code) = exit_code {
+        jni_env.exception_clear()?;
+        return Ok(Response {
+            exit_code: Some(exit_code),
+            ..Response::default()
+        });
+    }
+
+    let response_string: String = jni_env.get_string(&JString::from(response?))?.into();
+    Ok(Response::from_stdout(response_string))
+}
+
+impl Drop for PasswordWrapper {
+    fn drop(&mut self) {
+        // the global references need the thread to be attached to be released
+        drop(self.refs.take());
+        // SAFETY: every local reference of this thread lived in an executor frame,
+        // which are all gone by now.
+        unsafe { runtime::detach_current_thread() }
+    }
+}
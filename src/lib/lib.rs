@@ -1,6 +1,9 @@
-#[allow(dead_code, unused_imports)]
 // Public API
+pub mod attack;
+#[allow(dead_code, unused_imports)]
 pub mod child;
+pub mod engine;
 pub mod jni;
+pub mod oracle;
 pub mod parameters;
 pub mod symbols;
@@ -0,0 +1,224 @@
+use std::process::Output;
+
+use crate::oracle::{Matcher, Verdict};
+
+/// Everything a target answered to a single candidate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` while the target is still running or if it got killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The target stopped in the middle of its answer.
+    pub truncated: bool,
+}
+
+impl Response {
+    /// A response that consists of stdout only, e.g. the line a long-lived target printed.
+    pub fn from_stdout(stdout: impl Into<String>) -> Self {
+        Self {
+            stdout: stdout.into(),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<Output> for Response {
+    fn from(output: Output) -> Self {
+        Self {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            exit_code: output.status.code(),
+            // killed by a signal, so the output likely got cut off
+            truncated: output.status.code().is_none(),
+        }
+    }
+}
+
+/// Decides from a response whether the candidate was correct.
+/// Criteria can be combined with `and`, `or` & `not`.
+#[derive(Debug, Clone)]
+pub enum SuccessCriterion {
+    /// The target exited with exactly this code.
+    ExitCode(i32),
+    /// The target exited with any code but this one.
+    /// Not met while the target is still running or got killed by a signal.
+    ExitCodeNot(i32),
+    StderrEmpty,
+    StderrNotEmpty,
+    /// Stdout matches.
+    StdoutMatches(Matcher),
+    /// Stdout doesn't match, e.g. the failure banner is absent.
+    /// Guards the criterion: `judge` reports an empty or truncated response as an error,
+    /// as it lacks the banner without being a success.
+    StdoutLacks(Matcher),
+    /// Every criterion is met.
+    All(Vec<SuccessCriterion>),
+    /// At least one criterion is met.
+    Any(Vec<SuccessCriterion>),
+    Not(Box<SuccessCriterion>),
+}
+
+impl SuccessCriterion {
+    pub fn and(self, other: SuccessCriterion) -> Self {
+        match self {
+            SuccessCriterion::All(mut all) => {
+                all.push(other);
+                SuccessCriterion::All(all)
+            }
+            criterion => SuccessCriterion::All(vec![criterion, other]),
+        }
+    }
+
+    pub fn or(self, other: SuccessCriterion) -> Self {
+        match self {
+            SuccessCriterion::Any(mut any) => {
+                any.push(other);
+                SuccessCriterion::Any(any)
+            }
+            criterion => SuccessCriterion::Any(vec![criterion, other]),
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        SuccessCriterion::Not(Box::new(self))
+    }
+
+    pub fn is_met(&self, response: &Response) -> bool {
+        match self {
+            SuccessCriterion::ExitCode(code) => response.exit_code == Some(*code),
+            SuccessCriterion::ExitCodeNot(code) => {
+                matches!(response.exit_code, Some(exit_code) if exit_code != *code)
+            }
+            SuccessCriterion::StderrEmpty => response.stderr.is_empty(),
+            SuccessCriterion::StderrNotEmpty => !response.stderr.is_empty(),
+            SuccessCriterion::StdoutMatches(matcher) => matcher.is_match(&response.stdout),
+            SuccessCriterion::StdoutLacks(matcher) => !matcher.is_match(&response.stdout),
+            SuccessCriterion::All(all) => all.iter().all(|c| c.is_met(response)),
+            SuccessCriterion::Any(any) => any.iter().any(|c| c.is_met(response)),
+            SuccessCriterion::Not(criterion) => !criterion.is_met(response),
+        }
+    }
+
+    /// Turns the response into a verdict, checking the sanity guard first.
+    pub fn judge(&self, response: &Response) -> Verdict {
+        if self.is_guarded() {
+            if response.truncated {
+                return Verdict::Error("truncated response".into());
+            }
+            if response.stdout.trim().is_empty() {
+                return Verdict::Error("empty response".into());
+            }
+        }
+        Verdict::from(self.is_met(response))
+    }
+
+    /// Whether any part of the criterion looks at stderr.
+    pub fn reads_stderr(&self) -> bool {
+        match self {
+            SuccessCriterion::StderrEmpty | SuccessCriterion::StderrNotEmpty => true,
+            SuccessCriterion::All(criteria) | SuccessCriterion::Any(criteria) => {
+                criteria.iter().any(SuccessCriterion::reads_stderr)
+            }
+            SuccessCriterion::Not(criterion) => criterion.reads_stderr(),
+            _ => false,
+        }
+    }
+
+    /// Whether any part of the criterion looks at the exit code.
+    pub fn reads_exit_code(&self) -> bool {
+        match self {
+            SuccessCriterion::ExitCode(_) | SuccessCriterion::ExitCodeNot(_) => true,
+            SuccessCriterion::All(criteria) | SuccessCriterion::Any(criteria) => {
+                criteria.iter().any(SuccessCriterion::reads_exit_code)
+            }
+            SuccessCriterion::Not(criterion) => criterion.reads_exit_code(),
+            _ => false,
+        }
+    }
+
+    /// Whether any part of the criterion relies on the absence of output.
+    fn is_guarded(&self) -> bool {
+        match self {
+            SuccessCriterion::StdoutLacks(_) => true,
+            SuccessCriterion::All(criteria) | SuccessCriterion::Any(criteria) => {
+                criteria.iter().any(SuccessCriterion::is_guarded)
+            }
+            SuccessCriterion::Not(criterion) => criterion.is_guarded(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_criterion {
+    use super::*;
+
+    fn response(stdout: &str, stderr: &str, exit_code: Option<i32>) -> Response {
+        Response {
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+            exit_code,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let ok = response("", "", Some(0));
+        let failed = response("", "", Some(1));
+        let running = response("", "", None);
+
+        assert!(SuccessCriterion::ExitCode(0).is_met(&ok));
+        assert!(!SuccessCriterion::ExitCode(0).is_met(&failed));
+        assert!(!SuccessCriterion::ExitCode(0).is_met(&running));
+
+        assert!(SuccessCriterion::ExitCodeNot(1).is_met(&ok));
+        assert!(!SuccessCriterion::ExitCodeNot(1).is_met(&failed));
+        assert!(!SuccessCriterion::ExitCodeNot(1).is_met(&running));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let criterion = SuccessCriterion::ExitCode(0)
+            .and(SuccessCriterion::StderrEmpty)
+            .and(SuccessCriterion::StdoutMatches(Matcher::literal("welcome")).not());
+
+        assert!(criterion.is_met(&response("hi", "", Some(0))));
+        assert!(!criterion.is_met(&response("welcome", "", Some(0))));
+        assert!(!criterion.is_met(&response("hi", "wrong", Some(0))));
+
+        let criterion = SuccessCriterion::StderrNotEmpty.or(SuccessCriterion::ExitCode(3));
+        assert!(criterion.is_met(&response("", "", Some(3))));
+        assert!(criterion.is_met(&response("", "x", Some(0))));
+        assert!(!criterion.is_met(&response("", "", Some(0))));
+    }
+
+    #[test]
+    fn test_failure_banner_absent() {
+        let criterion = SuccessCriterion::StdoutLacks(Matcher::literal("Access denied"));
+
+        assert_eq!(
+            criterion.judge(&response("Access denied\n", "", None)),
+            Verdict::Incorrect
+        );
+        assert_eq!(
+            criterion.judge(&response("Hello there\n", "", None)),
+            Verdict::Correct
+        );
+        assert!(matches!(
+            criterion.judge(&response(" \n", "", None)),
+            Verdict::Error(_)
+        ));
+        let mut truncated = response("Acc", "", None);
+        truncated.truncated = true;
+        assert!(matches!(criterion.judge(&truncated), Verdict::Error(_)));
+
+        // unguarded criteria treat an empty response like any other
+        assert_eq!(
+            SuccessCriterion::ExitCode(0).judge(&response("", "", Some(0))),
+            Verdict::Correct
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+/// What a matcher looks for in the output.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// The output contains the text.
+    Literal(String),
+    /// The output contains the text, ignoring case. Stored in lowercase.
+    CaseInsensitive(String),
+    /// The regex matches somewhere in the output.
+    Regex(Regex),
+}
+
+/// Clean-ups applied to the output before it is matched.
+/// The pattern itself is used as given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Normalization {
+    /// Removes ANSI escape sequences, e.g. colour codes.
+    pub strip_ansi: bool,
+    /// Turns CRLF line endings into LF.
+    pub crlf: bool,
+    /// Removes leading & trailing whitespace.
+    pub trim: bool,
+    /// Replaces every run of whitespace with a single space.
+    pub collapse_whitespace: bool,
+}
+
+impl Normalization {
+    /// Leaves the output untouched.
+    pub const NONE: Self = Self {
+        strip_ansi: false,
+        crlf: false,
+        trim: false,
+        collapse_whitespace: false,
+    };
+
+    /// Applies every clean-up.
+    pub const ALL: Self = Self {
+        strip_ansi: true,
+        crlf: true,
+        trim: true,
+        collapse_whitespace: true,
+    };
+
+    /// Applies the enabled clean-ups. Doesn't allocate if none is enabled.
+    pub fn apply<'a>(&self, output: &'a str) -> Cow<'a, str> {
+        let mut output = Cow::Borrowed(output);
+        if self.strip_ansi && output.contains('\x1b') {
+            output = Cow::Owned(strip_ansi(&output));
+        }
+        if self.crlf && output.contains("\r\n") {
+            output = Cow::Owned(output.replace("\r\n", "\n"));
+        }
+        if self.collapse_whitespace {
+            output = Cow::Owned(output.split_whitespace().collect::<Vec<_>>().join(" "));
+        } else if self.trim {
+            output = match output {
+                Cow::Borrowed(output) => Cow::Borrowed(output.trim()),
+                Cow::Owned(output) => Cow::Owned(output.trim().to_owned()),
+            };
+        }
+        output
+    }
+}
+
+/// Decides whether an output matches a pattern, after normalizing the output.
+/// Shared by all backends, so that the same pattern behaves the same everywhere.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pattern: Pattern,
+    normalization: Normalization,
+}
+
+impl Matcher {
+    /// Case sensitive substring match.
+    pub fn literal(text: &str) -> Self {
+        Self::new(Pattern::Literal(text.to_owned()))
+    }
+
+    /// Substring match that ignores case.
+    pub fn case_insensitive(text: &str) -> Self {
+        Self::new(Pattern::CaseInsensitive(text.to_lowercase()))
+    }
+
+    /// Matches if the regex matches anywhere in the output.
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self::new(Pattern::Regex(Regex::new(pattern)?)))
+    }
+
+    fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            normalization: Normalization::NONE,
+        }
+    }
+
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    pub fn is_match(&self, output: &str) -> bool {
+        let output = self.normalization.apply(output);
+        match &self.pattern {
+            Pattern::Literal(text) => output.contains(text.as_str()),
+            Pattern::CaseInsensitive(text) => output.to_lowercase().contains(text.as_str()),
+            Pattern::Regex(regex) => regex.is_match(&output),
+        }
+    }
+}
+
+/// Removes CSI (`ESC [ ... final`), OSC (`ESC ] ... BEL` or `ESC ] ... ESC \`)
+/// & two-character escape sequences.
+fn strip_ansi(output: &str) -> String {
+    let mut stripped = String::with_capacity(output.len());
+    let mut chars = output.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char != '\x1b' {
+            stripped.push(char);
+            continue;
+        }
+        match chars.next() {
+            // parameters & intermediates until the final byte in @..=~
+            Some('[') => {
+                for char in chars.by_ref() {
+                    if ('@'..='~').contains(&char) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(char) = chars.next() {
+                    if char == '\x07' {
+                        break;
+                    }
+                    if char == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // ESC + single character; a trailing ESC is dropped
+            _ => {}
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests_matcher {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        assert!(Matcher::literal("Correct").is_match("> Correct!"));
+        assert!(!Matcher::literal("correct").is_match("> Correct!"));
+        assert!(Matcher::case_insensitive("cORRECT").is_match("> Correct!"));
+        assert!(Matcher::regex(r"^Welcome, \w+$")
+            .unwrap()
+            .is_match("Welcome, admin"));
+        assert!(Matcher::regex("(").is_err());
+    }
+
+    #[test]
+    fn test_normalization() {
+        let output = "\x1b[1;32mAccess   granted\x1b[0m \r\n";
+        assert!(!Matcher::literal("Access granted").is_match(output));
+        assert!(Matcher::literal("Access granted")
+            .with_normalization(Normalization::ALL)
+            .is_match(output));
+
+        let normalization = Normalization {
+            strip_ansi: true,
+            trim: true,
+            ..Normalization::NONE
+        };
+        assert_eq!(normalization.apply(output), "Access   granted");
+        assert_eq!(
+            Normalization {
+                crlf: true,
+                ..Normalization::NONE
+            }
+            .apply("a\r\nb\r\n"),
+            "a\nb\n"
+        );
+        assert_eq!(strip_ansi("\x1b]0;title\x07ok\x1b]8;;\x1b\\"), "ok");
+    }
+}
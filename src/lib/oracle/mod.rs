@@ -0,0 +1,53 @@
+mod criterion;
+mod matcher;
+
+// Export
+pub use criterion::{Response, SuccessCriterion};
+pub use matcher::{Matcher, Normalization, Pattern};
+
+/// The answer an oracle gives for a single candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The candidate is the password.
+    Correct,
+    /// The candidate was checked & rejected.
+    Incorrect,
+    /// The response can't be trusted, e.g. it was empty or cut off.
+    /// The search goes on, but the error gets counted.
+    Error(String),
+}
+
+impl Verdict {
+    pub const fn is_correct(&self) -> bool {
+        matches!(self, Verdict::Correct)
+    }
+}
+
+impl From<bool> for Verdict {
+    fn from(correct: bool) -> Self {
+        if correct {
+            Verdict::Correct
+        } else {
+            Verdict::Incorrect
+        }
+    }
+}
+
+/// Answers "is this candidate correct?" for the search loop.
+///
+/// Every worker thread creates its own instance via `create` from the shared config,
+/// calls `check` once per candidate & finally `close` once it is done.
+/// Instances are created on the worker thread itself, so they don't need to be `Send`.
+pub trait Oracle: Sized {
+    /// Read-only configuration shared by all workers.
+    type Config: Send + Sync + 'static;
+
+    /// Creates the instance for worker `tid`.
+    fn create(config: &Self::Config, tid: usize) -> Self;
+
+    /// Checks a single candidate.
+    fn check(&mut self, candidate: &str) -> Verdict;
+
+    /// Releases everything the instance holds on to.
+    fn close(self);
+}
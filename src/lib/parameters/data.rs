@@ -1,45 +1,77 @@
+use std::io;
+
+use crate::attack::Attack;
+use crate::engine::{Search, WorkerOptions};
 use crate::parameters::Spawn;
-use crate::symbols::combination_count;
+use crate::symbols::KeyspaceOverflow;
 
 #[derive(Debug, Clone)]
 pub struct CrackParam<I, S> {
     spawn: Spawn<I, S>,
-    charset: Box<[char]>,
-    min_length: u8,
-    max_length: u8,
-    total_combos: usize,
+    search: Search,
 }
 
 impl<I, S> CrackParam<I, S> {
     pub fn new(spawn: Spawn<I, S>, charset: Box<[char]>, min_length: u8, max_length: u8) -> Self {
-        let total_combos = combination_count(&charset, min_length, max_length);
-
         Self {
-            charset,
-            min_length,
-            max_length,
             spawn,
-            total_combos,
+            search: Search::new(charset, min_length, max_length),
         }
     }
 
+    /// Replaces the brute force over the charset, e.g. with a wordlist.
+    pub fn with_attack(mut self, attack: Attack) -> Self {
+        self.search = self.search.with_attack(attack);
+        self
+    }
+
+    /// Replaces the default of one worker per CPU but one, neither pinned nor at low priority.
+    pub fn with_worker_options(mut self, worker_options: WorkerOptions) -> Self {
+        self.search = self.search.with_worker_options(worker_options);
+        self
+    }
+
     pub const fn charset(&self) -> &[char] {
-        &self.charset
+        self.search.charset()
     }
 
     pub const fn max_length(&self) -> u8 {
-        self.max_length
+        self.search.max_length()
     }
 
     pub const fn min_length(&self) -> u8 {
-        self.min_length
+        self.search.min_length()
     }
 
     pub const fn spawn(&self) -> &Spawn<I, S> {
         &self.spawn
     }
 
-    pub fn total_combos(&self) -> usize {
-        self.total_combos
+    /// Number of combinations of the charset between the min & max length.
+    pub fn total_combos(&self) -> Result<u128, KeyspaceOverflow> {
+        self.search.total_combos()
+    }
+
+    /// The candidate at index `n` of the attack, see `Attack::nth_candidate`.
+    pub fn nth_candidate(&self, n: u128) -> io::Result<Option<String>> {
+        self.search.nth_candidate(n)
+    }
+
+    /// The index of `candidate` in the attack, see `Attack::candidate_index`.
+    pub fn candidate_index(&self, candidate: &str) -> io::Result<Option<u128>> {
+        self.search.candidate_index(candidate)
+    }
+
+    pub fn attack(&self) -> &Attack {
+        self.search.attack()
+    }
+
+    pub fn worker_options(&self) -> &WorkerOptions {
+        self.search.worker_options()
+    }
+
+    /// What the engine searches through, without the target.
+    pub fn search(&self) -> &Search {
+        &self.search
     }
 }
@@ -3,4 +3,4 @@ mod spawn;
 
 // Public API
 pub use data::CrackParam;
-pub use spawn::Spawn;
+pub use spawn::{Spawn, SpawnMode};
@@ -1,23 +1,71 @@
-use std::ffi::OsStr;
+use std::{ffi::OsStr, time::Duration};
+
+use crate::oracle::{Matcher, SuccessCriterion};
+
+/// How the target process is driven.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpawnMode {
+    /// One long-lived process per worker, fed one candidate per line.
+    #[default]
+    Persistent,
+    /// A fresh process for every candidate. For targets that exit after one try.
+    PerAttempt,
+}
 
 #[derive(Debug, Clone)]
 pub struct Spawn<I, S> {
     script: S,
     args: I,
     look_for_output: S,
+    mode: SpawnMode,
+    criterion: SuccessCriterion,
+    timeout: Duration,
 }
 
 impl<I: IntoIterator<Item = S> + std::clone::Clone, S: AsRef<OsStr> + std::clone::Clone>
     Spawn<I, S>
 {
+    /// By default a candidate is correct if stdout contains `look_for_output`.
     pub fn new(script: S, args: I, look_for_output: S) -> Self {
+        let criterion = SuccessCriterion::StdoutMatches(Matcher::literal(
+            &look_for_output.as_ref().to_string_lossy(),
+        ));
         Self {
             script,
             args,
             look_for_output,
+            mode: SpawnMode::default(),
+            criterion,
+            timeout: Duration::from_secs(10),
         }
     }
 
+    /// Replaces the default stdout match, e.g. to judge by exit code instead.
+    pub fn with_criterion(mut self, criterion: SuccessCriterion) -> Self {
+        self.criterion = criterion;
+        self
+    }
+
+    /// A candidate is correct if stdout does *not* match the failure pattern.
+    /// Empty or truncated responses are reported as oracle errors.
+    pub fn with_failure_pattern(self, failure: Matcher) -> Self {
+        self.with_criterion(SuccessCriterion::StdoutLacks(failure))
+    }
+
+    /// Sets how the target process is driven.
+    pub fn with_mode(mut self, mode: SpawnMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// How long the target may take for a candidate, a `SpawnMode::PerAttempt` process
+    /// to exit & a `SpawnMode::Persistent` one to answer. Past it the process is killed
+    /// & the candidate reported as an oracle error, 10 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     pub fn script(&self) -> &S {
         &self.script
     }
@@ -27,4 +75,13 @@ impl<I: IntoIterator<Item = S> + std::clone::Clone, S: AsRef<OsStr> + std::clone
     pub fn look_for_output(&self) -> &S {
         &self.look_for_output
     }
+    pub fn mode(&self) -> SpawnMode {
+        self.mode
+    }
+    pub fn criterion(&self) -> &SuccessCriterion {
+        &self.criterion
+    }
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
 }
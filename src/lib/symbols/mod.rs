@@ -1,5 +1,7 @@
 mod builder;
 
+use thiserror::Error;
+
 // Export
 pub use builder::Builder;
 
@@ -18,17 +20,26 @@ pub static LATIN_LC: [char; 26] = [
     't', 'u', 'v', 'w', 'x', 'y', 'z',
 ];
 
+/// The number of candidates of an attack doesn't fit into a `u128`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("the keyspace is larger than {}", u128::MAX)]
+pub struct KeyspaceOverflow;
+
 /// Calculates the amount of possible permutations given n symbols and m places.
-pub fn combination_count(charset: &[char], min_length: u8, max_length: u8) -> usize {
-    if min_length > max_length {
-        // TODO Substitutes string panic for Errors using crates "error-stack" & "thiserror"
-        panic!("min length must be <= max length")
-    }
-    let mut sum = 0;
+/// There are none if the min length is above the max length.
+pub fn combination_count(
+    charset: &[char],
+    min_length: u8,
+    max_length: u8,
+) -> Result<u128, KeyspaceOverflow> {
+    let mut sum: u128 = 0;
     for i in min_length..=max_length {
-        sum += charset.len().pow(i.into());
+        let count = (charset.len() as u128)
+            .checked_pow(i.into())
+            .ok_or(KeyspaceOverflow)?;
+        sum = sum.checked_add(count).ok_or(KeyspaceOverflow)?;
     }
-    sum
+    Ok(sum)
 }
 
 #[cfg(test)]
@@ -40,18 +51,32 @@ mod tests_symbols {
         let charset_empty: Box<[char]> = Box::from([]);
         let charset_one: Box<[char]> = Box::from(['a']);
 
-        assert_eq!(combination_count(&charset_empty, 0, 0), 1, "0 symbols.");
+        assert_eq!(combination_count(&charset_empty, 0, 0), Ok(1), "0 symbols.");
+
+        assert_eq!(
+            combination_count(&charset_one, 0, 1),
+            Ok(2),
+            "1 combination."
+        );
+    }
 
-        assert_eq!(combination_count(&charset_one, 0, 1), 2, "1 combination.");
+    #[test]
+    fn test_combinations_count_overflow() {
+        let printable: Box<[char]> = (' '..='~').collect();
+        assert_eq!(printable.len(), 95);
+        assert_eq!(
+            combination_count(&printable, 12, 12),
+            Ok(540_360_087_662_636_962_890_625)
+        );
+        assert_eq!(combination_count(&printable, 0, 255), Err(KeyspaceOverflow));
     }
 
     #[test]
-    #[should_panic]
-    fn test_combinations_count_panic() {
+    fn test_combinations_count_empty() {
         let charset: Box<[char]> = Box::from(['a']);
         assert_eq!(
             combination_count(&charset, 1, 0),
-            0,
+            Ok(0),
             "min length must be <= max length."
         )
     }